@@ -41,9 +41,16 @@
 
 #![allow(unused_variables, dead_code)]
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
 use rand::distr::Alphanumeric;
-use rand::{Rng, thread_rng};
+use rand::{Rng, RngCore, thread_rng};
 
 /// All possible errors of the [`UrlShortenerService`].
 #[derive(Debug, PartialEq)]
@@ -58,6 +65,199 @@ pub enum ShortenerError {
     /// This error occurs when the provided [`Slug`] does not map to any existing
     /// short link.
     SlugNotFound,
+
+    /// This error occurs when the provided [`Url`] is longer than
+    /// [`Config::max_url_len`] bytes.
+    UrlTooLong,
+
+    /// This error occurs when a redirect is attempted for a short link that
+    /// has already reached its maximum number of redirects.
+    RedirectLimitReached,
+
+    /// This error occurs when a redirect is attempted for a short link more
+    /// often than the [`Config::rate_limit`] allows.
+    RateLimited,
+
+    /// This error occurs when a conditional command is attempted for a short
+    /// link whose state doesn't match the expected one.
+    PreconditionFailed,
+
+    /// This error occurs when the provided [`Slug`] is rejected by the
+    /// [`SlugPolicy`] of the service.
+    InvalidSlug,
+
+    /// This error occurs when a saved state of the [`UrlShortenerService`]
+    /// cannot be loaded.
+    InvalidState,
+
+    /// This error occurs when a redirect is attempted for a short link that
+    /// has been expired.
+    LinkExpired,
+
+    /// This error occurs when a short link is attempted to be created while
+    /// the service already holds [`Config::max_links`] short links.
+    LinkLimitReached,
+
+    /// This error occurs when a [`UrlShortenerServiceBuilder`] is configured
+    /// with an invalid combination of options.
+    InvalidConfig,
+
+    /// This error occurs when a redirect is attempted for a short link whose
+    /// click count has reached [`u64::MAX`], while
+    /// [`Config::fail_on_counter_overflow`] is enabled.
+    CounterOverflow,
+
+    /// This error occurs when replayed [`Event`]s don't match the expected
+    /// checksum, see [`UrlShortenerService::events_checksum`].
+    ChecksumMismatch,
+
+    /// This error occurs when the provided [`Url`] points to a host within
+    /// one of the [`Config::blocked_domains`].
+    DomainBlocked,
+
+    /// This error occurs when a [`Url`] required to be shortened only once is
+    /// already shortened by the contained [`Slug`].
+    UrlAlreadyShortened(Slug),
+
+    /// This error occurs when short links redirecting to each other form a
+    /// cycle or a chain longer than allowed.
+    RedirectLoop,
+
+    /// This error occurs when the [`Url`] of a short link is attempted to be
+    /// changed while [`Config::immutable_links`] is enabled.
+    LinkImmutable,
+
+    /// This error occurs when a redirect is rejected by the redirect guard
+    /// of the service, see [`UrlShortenerService::set_redirect_guard`].
+    RedirectDenied,
+
+    /// This error occurs when no free [`Slug`] is generated within
+    /// [`MAX_SLUG_ATTEMPTS`] attempts, as the slug space is (nearly) used up.
+    SlugSpaceExhausted,
+}
+
+impl fmt::Display for ShortenerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUrl => write!(f, "invalid URL"),
+            Self::SlugAlreadyInUse => write!(f, "slug is already in use"),
+            Self::SlugNotFound => write!(f, "slug not found"),
+            Self::UrlTooLong => write!(f, "URL is too long"),
+            Self::RedirectLimitReached => write!(f, "redirect limit is reached"),
+            Self::RateLimited => write!(f, "too many redirects, try again later"),
+            Self::PreconditionFailed => write!(f, "precondition failed"),
+            Self::InvalidSlug => write!(f, "invalid slug"),
+            Self::InvalidState => write!(f, "invalid saved state"),
+            Self::LinkExpired => write!(f, "link is expired"),
+            Self::LinkLimitReached => write!(f, "link limit is reached"),
+            Self::InvalidConfig => write!(f, "invalid configuration"),
+            Self::CounterOverflow => write!(f, "click count overflow"),
+            Self::ChecksumMismatch => write!(f, "events checksum mismatch"),
+            Self::DomainBlocked => write!(f, "domain is blocked"),
+            Self::UrlAlreadyShortened(slug) => write!(f, "URL is already shortened as `{slug}`"),
+            Self::RedirectLoop => write!(f, "redirect loop detected"),
+            Self::LinkImmutable => write!(f, "link is immutable"),
+            Self::RedirectDenied => write!(f, "redirect denied"),
+            Self::SlugSpaceExhausted => write!(f, "no free slug could be generated"),
+        }
+    }
+}
+
+impl Error for ShortenerError {}
+
+/// Error of validating the event log with
+/// [`UrlShortenerService::validate_event_log`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogError {
+    /// Sequence number of the first dangling [`Event`], as returned by
+    /// [`UrlShortenerService::iter_events`].
+    pub index: usize,
+
+    /// [`EventKind`] of the first dangling [`Event`].
+    pub kind: EventKind,
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dangling {:?} event at index {}", self.kind, self.index)
+    }
+}
+
+impl Error for LogError {}
+
+/// Error of importing [`Event`]s with
+/// [`UrlShortenerService::import_events_jsonl`].
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq)]
+pub struct ImportError {
+    /// Number of the first malformed line, starting from `1`.
+    pub line: usize,
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed event at line {}", self.line)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Error for ImportError {}
+
+/// [`ShortenerError`] carrying the offending [`Slug`] or [`Url`], returned by
+/// the `try_*` methods of the [`UrlShortenerService`].
+#[derive(Debug, PartialEq)]
+pub enum DetailedError {
+    /// [`ShortenerError::SlugNotFound`] for the contained [`Slug`].
+    SlugNotFound(Slug),
+
+    /// [`ShortenerError::SlugAlreadyInUse`] for the contained [`Slug`].
+    SlugAlreadyInUse(Slug),
+
+    /// [`ShortenerError::InvalidUrl`] for the contained [`Url`].
+    InvalidUrl(Url),
+
+    /// Any other [`ShortenerError`].
+    Other(ShortenerError),
+}
+
+impl DetailedError {
+    /// Attaches the provided [`Slug`] and [`Url`] to the [`ShortenerError`],
+    /// if it's about them.
+    fn new(error: ShortenerError, slug: Option<&Slug>, url: Option<&Url>) -> Self {
+        match (error, slug, url) {
+            (ShortenerError::SlugNotFound, Some(slug), _) => Self::SlugNotFound(slug.clone()),
+            (ShortenerError::SlugAlreadyInUse, Some(slug), _) => {
+                Self::SlugAlreadyInUse(slug.clone())
+            }
+            (ShortenerError::InvalidUrl, _, Some(url)) => Self::InvalidUrl(url.clone()),
+            (error, _, _) => Self::Other(error),
+        }
+    }
+}
+
+impl fmt::Display for DetailedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SlugNotFound(slug) => write!(f, "slug `{slug}` not found"),
+            Self::SlugAlreadyInUse(slug) => write!(f, "slug `{slug}` is already in use"),
+            Self::InvalidUrl(url) => write!(f, "invalid URL `{url}`"),
+            Self::Other(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for DetailedError {}
+
+impl From<DetailedError> for ShortenerError {
+    fn from(error: DetailedError) -> Self {
+        match error {
+            DetailedError::SlugNotFound(_) => Self::SlugNotFound,
+            DetailedError::SlugAlreadyInUse(_) => Self::SlugAlreadyInUse,
+            DetailedError::InvalidUrl(_) => Self::InvalidUrl,
+            DetailedError::Other(error) => error,
+        }
+    }
 }
 
 /// Represents the different types of events that can occur within the
@@ -65,7 +265,9 @@ pub enum ShortenerError {
 ///
 /// Using event sourcing, each change or action taken is logged as an event.
 /// This allows the current state to be reconstructed by replaying events.
-enum Event {
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event {
     /// Event indicating that a new short link has been created.
     ///
     /// Contains the [`Slug`] and the original [`Url`] for the newly created short link.
@@ -74,6 +276,112 @@ enum Event {
         slug: Slug,
         /// The original URL that the short link points to.
         url: Url,
+        /// Arbitrary key-value metadata attached to the short link.
+        meta: HashMap<String, String>,
+        /// Maximum number of redirects allowed for the short link, if any.
+        max_redirects: Option<u64>,
+        /// Idempotency key of the command the short link has been created by,
+        /// if any.
+        #[cfg_attr(feature = "serde", serde(default))]
+        idempotency_key: Option<String>,
+        /// Whether the clicks of the short link are not counted.
+        #[cfg_attr(feature = "serde", serde(default))]
+        untracked: bool,
+        /// Time the short link has been created at.
+        at: SystemTime,
+    },
+
+    /// Event indicating that the [`Url`] of an existing short link has been
+    /// changed.
+    LinkUrlChanged {
+        /// The unique identifier for the changed short link.
+        slug: Slug,
+        /// The new URL that the short link points to.
+        url: Url,
+    },
+
+    /// Event indicating that the [`Slug`] of an existing short link has been
+    /// renamed, keeping its [`Url`] and stats.
+    SlugRenamed {
+        /// The previous unique identifier of the short link.
+        old: Slug,
+        /// The new unique identifier of the short link.
+        new: Slug,
+    },
+
+    /// Event indicating that an alias [`Slug`] has been created for an
+    /// existing short link, sharing its [`Url`] and click count.
+    AliasCreated {
+        /// The alias of the short link.
+        alias: Slug,
+        /// The unique identifier of the short link the alias points to.
+        canonical: Slug,
+    },
+
+    /// Event indicating that a short link (or an alias) has been deleted
+    /// along with its stats.
+    LinkDeleted {
+        /// The unique identifier for the deleted short link.
+        slug: Slug,
+    },
+
+    /// Event indicating that a short link has been expired, so it no longer
+    /// redirects while its stats are kept.
+    LinkExpired {
+        /// The unique identifier for the expired short link.
+        slug: Slug,
+    },
+
+    /// Event indicating that counting the clicks of a short link has been
+    /// enabled or disabled.
+    ClickTrackingChanged {
+        /// The unique identifier for the short link.
+        slug: Slug,
+        /// Indicates whether the clicks of the short link are counted.
+        track: bool,
+    },
+
+    /// Event indicating that the free-text description of a short link has
+    /// been set, replacing the previous one.
+    DescriptionSet {
+        /// The unique identifier for the short link.
+        slug: Slug,
+        /// The description of the short link.
+        text: String,
+    },
+
+    /// Event indicating that a previously deleted short link has been
+    /// restored.
+    LinkRestored {
+        /// The unique identifier for the restored short link.
+        slug: Slug,
+        /// The URL that the restored short link points to.
+        url: Url,
+    },
+
+    /// Event indicating that a batch of redirects has been recorded for a
+    /// short link at once, e.g. when importing click counts.
+    RedirectsRecorded {
+        /// The unique identifier for the short link.
+        slug: Slug,
+        /// Count of the recorded redirects.
+        count: u64,
+    },
+
+    /// Event indicating that the redirect count of a short link has been
+    /// corrected to an exact value, overriding all the prior redirects.
+    RedirectCountSet {
+        /// The unique identifier for the short link.
+        slug: Slug,
+        /// New redirect count of the short link.
+        count: u64,
+    },
+
+    /// Event indicating that the redirect count of a short link has been
+    /// reset to zero, e.g. at the start of a new campaign period.
+    ClicksReset {
+        /// The unique identifier for the short link.
+        slug: Slug,
     },
 
     /// Event indicating that a redirect action has occurred for a short link.
@@ -82,20 +390,305 @@ enum Event {
     LinkRedirected {
         /// The unique identifier for the short link that was used in the redirect.
         slug: Slug,
+        /// The source the redirect came from, if known.
+        referrer: Option<String>,
+        /// Code of the country the redirect came from, if known.
+        #[cfg_attr(feature = "serde", serde(default))]
+        country: Option<String>,
+        /// Time the redirect has occurred at.
+        at: SystemTime,
+    },
+}
+
+impl Event {
+    /// Returns the [`EventKind`] of this [`Event`].
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::LinkCreated { .. } => EventKind::LinkCreated,
+            Self::LinkUrlChanged { .. } => EventKind::LinkUrlChanged,
+            Self::SlugRenamed { .. } => EventKind::SlugRenamed,
+            Self::AliasCreated { .. } => EventKind::AliasCreated,
+            Self::LinkDeleted { .. } => EventKind::LinkDeleted,
+            Self::LinkExpired { .. } => EventKind::LinkExpired,
+            Self::ClickTrackingChanged { .. } => EventKind::ClickTrackingChanged,
+            Self::DescriptionSet { .. } => EventKind::DescriptionSet,
+            Self::LinkRestored { .. } => EventKind::LinkRestored,
+            Self::RedirectsRecorded { .. } => EventKind::RedirectsRecorded,
+            Self::RedirectCountSet { .. } => EventKind::RedirectCountSet,
+            Self::ClicksReset { .. } => EventKind::ClicksReset,
+            Self::LinkRedirected { .. } => EventKind::LinkRedirected,
+        }
+    }
+}
+
+/// Kinds of [`Event`]s, mirroring its variants without their data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// Kind of [`Event::LinkCreated`].
+    LinkCreated,
+
+    /// Kind of [`Event::LinkUrlChanged`].
+    LinkUrlChanged,
+
+    /// Kind of [`Event::SlugRenamed`].
+    SlugRenamed,
+
+    /// Kind of [`Event::AliasCreated`].
+    AliasCreated,
+
+    /// Kind of [`Event::LinkDeleted`].
+    LinkDeleted,
+
+    /// Kind of [`Event::LinkExpired`].
+    LinkExpired,
+
+    /// Kind of [`Event::ClickTrackingChanged`].
+    ClickTrackingChanged,
+
+    /// Kind of [`Event::DescriptionSet`].
+    DescriptionSet,
+
+    /// Kind of [`Event::LinkRestored`].
+    LinkRestored,
+
+    /// Kind of [`Event::RedirectsRecorded`].
+    RedirectsRecorded,
+
+    /// Kind of [`Event::RedirectCountSet`].
+    RedirectCountSet,
+
+    /// Kind of [`Event::ClicksReset`].
+    ClicksReset,
+
+    /// Kind of [`Event::LinkRedirected`].
+    LinkRedirected,
+}
+
+/// Owned mirror of an [`Event`], serving as a stable interchange type of the
+/// event log, independent of the internal [`Event`] and of `serde`.
+///
+/// See [`UrlShortenerService::export_public_events`] and
+/// [`UrlShortenerService::from_public_events`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum PublicEvent {
+    /// Mirror of [`Event::LinkCreated`].
+    LinkCreated {
+        /// The unique identifier for the short link.
+        slug: Slug,
+        /// The original URL that the short link points to.
+        url: Url,
+        /// Arbitrary key-value metadata attached to the short link.
+        meta: HashMap<String, String>,
+        /// Maximum number of redirects allowed for the short link, if any.
+        max_redirects: Option<u64>,
+        /// Idempotency key of the command the short link has been created by,
+        /// if any.
+        idempotency_key: Option<String>,
+        /// Whether the clicks of the short link are not counted.
+        untracked: bool,
+        /// Time the short link has been created at.
+        at: SystemTime,
+    },
+
+    /// Mirror of [`Event::LinkUrlChanged`].
+    LinkUrlChanged {
+        /// The unique identifier for the changed short link.
+        slug: Slug,
+        /// The new URL that the short link points to.
+        url: Url,
+    },
+
+    /// Mirror of [`Event::SlugRenamed`].
+    SlugRenamed {
+        /// The previous unique identifier of the short link.
+        old: Slug,
+        /// The new unique identifier of the short link.
+        new: Slug,
+    },
+
+    /// Mirror of [`Event::AliasCreated`].
+    AliasCreated {
+        /// The alias pointing to the short link.
+        alias: Slug,
+        /// The unique identifier of the short link the alias points to.
+        canonical: Slug,
+    },
+
+    /// Mirror of [`Event::LinkDeleted`].
+    LinkDeleted {
+        /// The unique identifier for the deleted short link.
+        slug: Slug,
+    },
+
+    /// Mirror of [`Event::LinkExpired`].
+    LinkExpired {
+        /// The unique identifier for the expired short link.
+        slug: Slug,
+    },
+
+    /// Mirror of [`Event::ClickTrackingChanged`].
+    ClickTrackingChanged {
+        /// The unique identifier for the short link.
+        slug: Slug,
+        /// Indicates whether the clicks of the short link are counted.
+        track: bool,
+    },
+
+    /// Mirror of [`Event::DescriptionSet`].
+    DescriptionSet {
+        /// The unique identifier for the short link.
+        slug: Slug,
+        /// The description of the short link.
+        text: String,
+    },
+
+    /// Mirror of [`Event::LinkRestored`].
+    LinkRestored {
+        /// The unique identifier for the restored short link.
+        slug: Slug,
+        /// The URL that the restored short link points to.
+        url: Url,
+    },
+
+    /// Mirror of [`Event::RedirectsRecorded`].
+    RedirectsRecorded {
+        /// The unique identifier for the short link.
+        slug: Slug,
+        /// Count of the recorded redirects.
+        count: u64,
+    },
+
+    /// Mirror of [`Event::RedirectCountSet`].
+    RedirectCountSet {
+        /// The unique identifier for the short link.
+        slug: Slug,
+        /// New redirect count of the short link.
+        count: u64,
+    },
+
+    /// Mirror of [`Event::ClicksReset`].
+    ClicksReset {
+        /// The unique identifier for the short link.
+        slug: Slug,
+    },
+
+    /// Mirror of [`Event::LinkRedirected`].
+    LinkRedirected {
+        /// The unique identifier for the short link that was used in the redirect.
+        slug: Slug,
+        /// The source the redirect came from, if known.
+        referrer: Option<String>,
+        /// Code of the country the redirect came from, if known.
+        country: Option<String>,
+        /// Time the redirect has occurred at.
+        at: SystemTime,
     },
 }
 
+impl From<&Event> for PublicEvent {
+    fn from(event: &Event) -> Self {
+        match event.clone() {
+            Event::LinkCreated { slug, url, meta, max_redirects, idempotency_key, untracked, at } => {
+                Self::LinkCreated { slug, url, meta, max_redirects, idempotency_key, untracked, at }
+            }
+            Event::LinkUrlChanged { slug, url } => Self::LinkUrlChanged { slug, url },
+            Event::SlugRenamed { old, new } => Self::SlugRenamed { old, new },
+            Event::AliasCreated { alias, canonical } => Self::AliasCreated { alias, canonical },
+            Event::LinkDeleted { slug } => Self::LinkDeleted { slug },
+            Event::LinkExpired { slug } => Self::LinkExpired { slug },
+            Event::ClickTrackingChanged { slug, track } => Self::ClickTrackingChanged { slug, track },
+            Event::DescriptionSet { slug, text } => Self::DescriptionSet { slug, text },
+            Event::LinkRestored { slug, url } => Self::LinkRestored { slug, url },
+            Event::RedirectsRecorded { slug, count } => Self::RedirectsRecorded { slug, count },
+            Event::RedirectCountSet { slug, count } => Self::RedirectCountSet { slug, count },
+            Event::ClicksReset { slug } => Self::ClicksReset { slug },
+            Event::LinkRedirected { slug, referrer, country, at } => {
+                Self::LinkRedirected { slug, referrer, country, at }
+            }
+        }
+    }
+}
+
+impl From<PublicEvent> for Event {
+    fn from(event: PublicEvent) -> Self {
+        match event {
+            PublicEvent::LinkCreated { slug, url, meta, max_redirects, idempotency_key, untracked, at } => {
+                Self::LinkCreated { slug, url, meta, max_redirects, idempotency_key, untracked, at }
+            }
+            PublicEvent::LinkUrlChanged { slug, url } => Self::LinkUrlChanged { slug, url },
+            PublicEvent::SlugRenamed { old, new } => Self::SlugRenamed { old, new },
+            PublicEvent::AliasCreated { alias, canonical } => Self::AliasCreated { alias, canonical },
+            PublicEvent::LinkDeleted { slug } => Self::LinkDeleted { slug },
+            PublicEvent::LinkExpired { slug } => Self::LinkExpired { slug },
+            PublicEvent::ClickTrackingChanged { slug, track } => {
+                Self::ClickTrackingChanged { slug, track }
+            }
+            PublicEvent::DescriptionSet { slug, text } => Self::DescriptionSet { slug, text },
+            PublicEvent::LinkRestored { slug, url } => Self::LinkRestored { slug, url },
+            PublicEvent::RedirectsRecorded { slug, count } => Self::RedirectsRecorded { slug, count },
+            PublicEvent::RedirectCountSet { slug, count } => Self::RedirectCountSet { slug, count },
+            PublicEvent::ClicksReset { slug } => Self::ClicksReset { slug },
+            PublicEvent::LinkRedirected { slug, referrer, country, at } => {
+                Self::LinkRedirected { slug, referrer, country, at }
+            }
+        }
+    }
+}
+
 /// A unique string (or alias) that represents the shortened version of the
 /// URL.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Slug(pub String);
 
 /// The original URL that the short link points to.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Url(pub String);
 
+impl fmt::Display for Slug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Slug {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.into()))
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Url {
+    type Err = ShortenerError;
+
+    /// Parses an absolute [`Url`] without whitespace or control characters.
+    ///
+    /// Unlike the [`UrlShortenerService`], any scheme and length is accepted.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::InvalidUrl`] if the provided string is not
+    /// a valid [`Url`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if split_scheme(s).is_none() || s.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(ShortenerError::InvalidUrl);
+        }
+        Ok(Self(s.into()))
+    }
+}
+
 /// Shortened URL representation.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ShortLink {
     /// A unique string (or alias) that represents the shortened version of the
     /// URL.
@@ -105,6 +698,17 @@ pub struct ShortLink {
     pub url: Url,
 }
 
+/// Outcome of creating a [`ShortLink`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateOutcome {
+    /// Created or already existing [`ShortLink`].
+    pub link: ShortLink,
+
+    /// Indicates whether the [`ShortLink`] has been newly created, rather
+    /// than an already existing one being returned.
+    pub created: bool,
+}
+
 /// Statistics of the [`ShortLink`].
 #[derive(Debug, Clone, PartialEq)]
 pub struct Stats {
@@ -115,6 +719,20 @@ pub struct Stats {
     pub redirects: u64,
 }
 
+impl Stats {
+    /// Returns the [`ShortLink`] these [`Stats`] are related to.
+    pub fn into_link(self) -> ShortLink {
+        self.link
+    }
+}
+
+impl From<ShortLink> for Stats {
+    /// Creates [`Stats`] of the never redirected [`ShortLink`].
+    fn from(link: ShortLink) -> Self {
+        Self { link, redirects: 0 }
+    }
+}
+
 /// Commands for CQRS.
 pub mod commands {
     use super::{ShortLink, ShortenerError, Slug, Url};
@@ -161,7 +779,7 @@ pub mod commands {
 
 /// Queries for CQRS
 pub mod queries {
-    use super::{ShortenerError, Slug, Stats};
+    use super::{ShortenerError, Slug, Stats, Url};
 
     /// Trait for query handlers.
     pub trait QueryHandler {
@@ -170,257 +788,6868 @@ pub mod queries {
         ///
         /// [`ShortLink`]: super::ShortLink
         fn get_stats(&self, slug: Slug) -> Result<Stats, ShortenerError>;
+
+        /// Returns the [`Url`] the [`ShortLink`] redirects to, without
+        /// counting a redirect.
+        ///
+        /// [`ShortLink`]: super::ShortLink
+        fn peek(&self, slug: Slug) -> Result<Url, ShortenerError> {
+            self.get_stats(slug).map(|stats| stats.link.url)
+        }
     }
 }
 
-/// CQRS and Event Sourcing-based service implementation
-pub struct UrlShortenerService {
-    events: Vec<Event>,
+/// Maximum number of [`Slug`]s generated for a single short link before
+/// giving up with a [`ShortenerError::SlugSpaceExhausted`].
+pub const MAX_SLUG_ATTEMPTS: usize = 100;
+
+/// Referrer key under which redirects without a known referrer are counted.
+pub const DIRECT_REFERRER: &str = "direct";
+
+/// Country key under which redirects without a known country are counted.
+pub const UNKNOWN_COUNTRY: &str = "??";
+
+/// Read side projection of the [`UrlShortenerService`].
+///
+/// It is updated only by applying [`Event`]s, so it can be fed by the event
+/// stream of any [`UrlShortenerService`] and queried independently of it.
+#[derive(Clone, Debug, Default)]
+pub struct ReadModel {
     links: HashMap<Slug, Url>,
     click_counts: HashMap<Slug, u64>,
+    meta: HashMap<Slug, HashMap<String, String>>,
+    referrers: HashMap<Slug, HashMap<String, u64>>,
+    countries: HashMap<Slug, HashMap<String, u64>>,
+    descriptions: HashMap<Slug, String>,
+    /// Canonical slugs of the aliases.
+    aliases: HashMap<Slug, Slug>,
 }
 
-impl UrlShortenerService {
-    /// Creates a new instance of the service
+impl ReadModel {
+    /// Creates a new empty [`ReadModel`].
     pub fn new() -> Self {
-        Self {
-            events: vec![],
-            links: HashMap::new(),
-            click_counts: HashMap::new(),
+        Self::default()
+    }
+
+    /// Applies the provided [`Event`] to this projection.
+    pub fn apply(&mut self, event: &Event) {
+        match event {
+            Event::LinkCreated { slug, url, meta, .. } => {
+                self.links.insert(slug.clone(), url.clone());
+                self.meta.insert(slug.clone(), meta.clone());
+            }
+            Event::LinkUrlChanged { slug, url } | Event::LinkRestored { slug, url } => {
+                self.links.insert(slug.clone(), url.clone());
+            }
+            Event::AliasCreated { alias, canonical } => {
+                self.aliases.insert(alias.clone(), canonical.clone());
+            }
+            Event::LinkDeleted { slug } => {
+                if self.aliases.remove(slug).is_none() {
+                    self.links.remove(slug);
+                    self.click_counts.remove(slug);
+                    self.meta.remove(slug);
+                    self.referrers.remove(slug);
+                    self.countries.remove(slug);
+                    self.descriptions.remove(slug);
+                    self.aliases.retain(|_, canonical| canonical != slug);
+                }
+            }
+            Event::DescriptionSet { slug, text } => {
+                self.descriptions.insert(slug.clone(), text.clone());
+            }
+            Event::LinkExpired { .. } | Event::ClickTrackingChanged { .. } => {}
+            Event::SlugRenamed { old, new } => {
+                if let Some(url) = self.links.remove(old) {
+                    self.links.insert(new.clone(), url);
+                }
+                for canonical in self.aliases.values_mut().filter(|canonical| *canonical == old) {
+                    *canonical = new.clone();
+                }
+                if let Some(count) = self.click_counts.remove(old) {
+                    self.click_counts.insert(new.clone(), count);
+                }
+                if let Some(meta) = self.meta.remove(old) {
+                    self.meta.insert(new.clone(), meta);
+                }
+                if let Some(referrers) = self.referrers.remove(old) {
+                    self.referrers.insert(new.clone(), referrers);
+                }
+                if let Some(countries) = self.countries.remove(old) {
+                    self.countries.insert(new.clone(), countries);
+                }
+                if let Some(description) = self.descriptions.remove(old) {
+                    self.descriptions.insert(new.clone(), description);
+                }
+            }
+            Event::RedirectsRecorded { slug, count } => {
+                let total = self.click_counts.entry(slug.clone()).or_insert(0);
+                *total = total.saturating_add(*count);
+            }
+            Event::RedirectCountSet { slug, count } => {
+                self.click_counts.insert(slug.clone(), *count);
+            }
+            Event::ClicksReset { slug } => {
+                self.click_counts.insert(slug.clone(), 0);
+            }
+            Event::LinkRedirected { slug, referrer, country, .. } => {
+                let total = self.click_counts.entry(slug.clone()).or_insert(0);
+                *total = total.saturating_add(1);
+                let referrer = referrer.as_deref().unwrap_or(DIRECT_REFERRER);
+                *self.referrers
+                    .entry(slug.clone())
+                    .or_default()
+                    .entry(referrer.to_owned())
+                    .or_insert(0) += 1;
+                let country = country.as_deref().unwrap_or(UNKNOWN_COUNTRY);
+                *self.countries
+                    .entry(slug.clone())
+                    .or_default()
+                    .entry(country.to_owned())
+                    .or_insert(0) += 1;
+            }
         }
     }
 
-    /// Generates a random slug using a UUID.
+    /// Returns the [`ShortLink`] of the provided [`Slug`] without counting a
+    /// redirect.
     ///
-    /// This function creates a new UUID (Universally Unique Identifier) and extracts
-    /// the first part before the first hyphen. The generated slug string is wrapped
-    /// in a `Slug` struct to represent the generated slug.
+    /// ## Errors
     ///
-    /// # Returns
-    /// A `Slug` struct containing a randomly generated slug string.
-    fn generate_random_slug() -> Slug {
-        let slug: String = thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(8)
-            .map(char::from) // Convert to char
-            .collect(); // Collect into a String
-        Slug(slug)
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn resolve(&self, slug: Slug) -> Result<ShortLink, ShortenerError> {
+        let canonical = self.aliases.get(&slug).unwrap_or(&slug);
+        let url = self.links.get(canonical).ok_or(ShortenerError::SlugNotFound)?.clone();
+        Ok(ShortLink { slug, url })
     }
 
-    /// Creates a LinkCreated event and adds it to the list of events
-    fn add_link_created_event(&mut self, slug: Slug, url: Url) {
-        self.events.push(Event::LinkCreated {
-            slug: slug.clone(),
-            url: url.clone(),
-        });
-        self.links.insert(slug, url);
+    /// Returns the metadata attached to the [`ShortLink`] on its creation.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn get_meta(&self, slug: Slug) -> Result<HashMap<String, String>, ShortenerError> {
+        let slug = self.aliases.get(&slug).unwrap_or(&slug);
+        if !self.links.contains_key(slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        Ok(self.meta.get(slug).cloned().unwrap_or_default())
+    }
+
+    /// Returns the histogram of the referrers the redirects of the
+    /// [`ShortLink`] came from.
+    ///
+    /// Redirects without a known referrer are counted under the
+    /// [`DIRECT_REFERRER`] key.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn referrers(&self, slug: Slug) -> Result<HashMap<String, u64>, ShortenerError> {
+        let slug = self.aliases.get(&slug).unwrap_or(&slug);
+        if !self.links.contains_key(slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        Ok(self.referrers.get(slug).cloned().unwrap_or_default())
+    }
+
+    /// Returns the histogram of the countries the redirects of the
+    /// [`ShortLink`] came from.
+    ///
+    /// Redirects without a known country are counted under the
+    /// [`UNKNOWN_COUNTRY`] key.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn country_breakdown(&self, slug: Slug) -> Result<HashMap<String, u64>, ShortenerError> {
+        let slug = self.aliases.get(&slug).unwrap_or(&slug);
+        if !self.links.contains_key(slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        Ok(self.countries.get(slug).cloned().unwrap_or_default())
+    }
+
+    /// Returns the description of the [`ShortLink`], if it's set.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn get_description(&self, slug: Slug) -> Result<Option<String>, ShortenerError> {
+        let slug = self.aliases.get(&slug).unwrap_or(&slug);
+        if !self.links.contains_key(slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        Ok(self.descriptions.get(slug).cloned())
+    }
+
+    /// Returns an iterator over every [`ShortLink`] [`Slug`] and its number
+    /// of redirects, including never redirected ones.
+    pub fn redirect_iter(&self) -> impl Iterator<Item = (&Slug, u64)> {
+        self.links
+            .keys()
+            .map(|slug| (slug, *self.click_counts.get(slug).unwrap_or(&0)))
+    }
+}
+
+impl queries::QueryHandler for ReadModel {
+    fn get_stats(&self, slug: Slug) -> Result<Stats, ShortenerError> {
+        let slug = self.aliases.get(&slug).cloned().unwrap_or(slug);
+        let url = self.links.get(&slug).ok_or(ShortenerError::SlugNotFound)?.clone();
+        let redirects = *self.click_counts.get(&slug).unwrap_or(&0);
+
+        Ok(Stats {
+            link: ShortLink { slug, url },
+            redirects
+        })
+    }
+}
+
+/// Snapshot of the [`UrlShortenerService`] size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ServiceSummary {
+    /// Count of existing [`ShortLink`]s.
+    pub link_count: usize,
+
+    /// Count of recorded [`Event`]s.
+    pub total_events: usize,
+
+    /// Count of redirects across all the [`ShortLink`]s.
+    pub total_redirects: u64,
+}
+
+/// Entry of the activity timeline of a [`ShortLink`], see
+/// [`UrlShortenerService::timeline`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimelineEntry {
+    /// Name of the [`EventKind`] of the [`Event`], like `"LinkCreated"`.
+    pub kind: String,
+
+    /// Time the [`Event`] has occurred at, if it's recorded.
+    pub at: Option<SystemTime>,
+}
+
+/// Distribution of the redirect counts across all the [`ShortLink`]s, see
+/// [`UrlShortenerService::redirect_distribution`].
+///
+/// Percentiles are computed with the nearest-rank method, so they are always
+/// one of the redirect counts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Distribution {
+    /// Lowest redirect count.
+    pub min: u64,
+
+    /// Highest redirect count.
+    pub max: u64,
+
+    /// 50th percentile of the redirect counts.
+    pub median: u64,
+
+    /// 95th percentile of the redirect counts.
+    pub p95: u64,
+}
+
+/// Divergences of the state of the [`UrlShortenerService`] from the one of
+/// replaying some [`Event`]s, see
+/// [`UrlShortenerService::diff_against_replay`].
+///
+/// Every list is sorted by the [`Slug`]s.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplayDiff {
+    /// [`Slug`]s of the [`ShortLink`]s existing only in the current state.
+    pub only_current: Vec<Slug>,
+
+    /// [`Slug`]s of the [`ShortLink`]s existing only in the replayed state.
+    pub only_replay: Vec<Slug>,
+
+    /// [`Slug`]s of the [`ShortLink`]s pointing to different [`Url`]s.
+    pub different_urls: Vec<Slug>,
+
+    /// [`Slug`]s of the [`ShortLink`]s having different redirect counts.
+    pub different_counts: Vec<Slug>,
+}
+
+impl ReplayDiff {
+    /// Indicates whether no divergences have been found.
+    pub fn is_empty(&self) -> bool {
+        self.only_current.is_empty()
+            && self.only_replay.is_empty()
+            && self.different_urls.is_empty()
+            && self.different_counts.is_empty()
+    }
+}
+
+/// Strategy of generating [`Slug`]s for short links created without a custom
+/// one.
+pub trait SlugGenerator {
+    /// Generates a new [`Slug`] candidate.
+    ///
+    /// The candidate may already be in use, in which case the
+    /// [`UrlShortenerService`] asks for another one.
+    fn generate(&mut self) -> Slug;
+}
+
+/// Default [`SlugGenerator`] producing random alphanumeric [`Slug`]s of 8
+/// characters.
+#[derive(Debug, Default)]
+pub struct RandomSlugGenerator;
+
+impl SlugGenerator for RandomSlugGenerator {
+    fn generate(&mut self) -> Slug {
+        let slug: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(8)
+            .map(char::from) // Convert to char
+            .collect(); // Collect into a String
+        Slug(slug)
+    }
+}
+
+/// [`SlugGenerator`] producing random 8 character alphanumeric [`Slug`]s from
+/// the provided random source, allowing reproducible slug sequences with a
+/// seeded one.
+pub struct RngSlugGenerator<R>(pub R);
+
+impl<R: RngCore> SlugGenerator for RngSlugGenerator<R> {
+    fn generate(&mut self) -> Slug {
+        let slug: String = (&mut self.0)
+            .sample_iter(&Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect();
+        Slug(slug)
+    }
+}
+
+/// [`SlugGenerator`] producing random [`Slug`]s of the configured length from
+/// the configured alphabet, built by the [`UrlShortenerServiceBuilder`].
+struct AlphabetSlugGenerator {
+    alphabet: Vec<char>,
+    length: usize,
+}
+
+impl SlugGenerator for AlphabetSlugGenerator {
+    fn generate(&mut self) -> Slug {
+        let mut rng = thread_rng();
+        let slug = (0..self.length)
+            .map(|_| self.alphabet[rng.gen_range(0..self.alphabet.len())])
+            .collect();
+        Slug(slug)
+    }
+}
+
+/// Rules of validating custom [`Slug`]s provided by users.
+pub trait SlugPolicy {
+    /// Checks whether the provided slug is allowed to be used.
+    fn is_valid(&self, slug: &str) -> bool;
+}
+
+/// Default [`SlugPolicy`] allowing non-empty slugs consisting of ASCII
+/// letters, digits, `_` and `-`.
+#[derive(Debug, Default)]
+pub struct DefaultSlugPolicy;
+
+impl SlugPolicy for DefaultSlugPolicy {
+    fn is_valid(&self, slug: &str) -> bool {
+        !slug.is_empty()
+            && slug.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+}
+
+/// Visitor of the [`Event`]s replayed by
+/// [`UrlShortenerService::replay_into`], allowing to build custom projections.
+///
+/// All the methods do nothing by default, so visitors may implement only the
+/// ones they care about.
+pub trait EventVisitor {
+    /// Visits the creation of a short link.
+    fn on_created(&mut self, slug: &Slug, url: &Url) {}
+
+    /// Visits the change of the [`Url`] of a short link.
+    fn on_url_changed(&mut self, slug: &Slug, url: &Url) {}
+
+    /// Visits a redirect by a short link.
+    fn on_redirected(&mut self, slug: &Slug) {}
+}
+
+/// Durable storage of the [`Event`]s appended to the event log of the
+/// [`UrlShortenerService`], like a file or an external database.
+pub trait EventSink {
+    /// Persists the provided [`Event`], once the state of the
+    /// [`UrlShortenerService`] has been updated with it.
+    fn persist(&mut self, event: &PublicEvent);
+}
+
+/// Source of the current time for time-based features of the
+/// [`UrlShortenerService`].
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// Default [`Clock`] returning the system time.
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Strategy of generating [`Slug`]s for short links created without a custom
+/// one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SlugStrategy {
+    /// Slugs are produced by the [`SlugGenerator`] of the
+    /// [`UrlShortenerService`].
+    #[default]
+    Random,
+
+    /// Slugs are derived from a hash of the [`Url`], so the same [`Url`]
+    /// always yields the same [`Slug`] and creating it again returns the
+    /// existing [`ShortLink`].
+    ///
+    /// Hash collisions with a different [`Url`] are disambiguated by a
+    /// numeric suffix.
+    Hashed,
+}
+
+/// Service-wide token bucket throttling the redirects of all the
+/// [`ShortLink`]s together, see [`Config::global_throttle`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GlobalThrottle {
+    /// Maximum number of redirects the bucket holds, allowing bursts.
+    pub capacity: u64,
+
+    /// Time it takes to refill the bucket with a single redirect.
+    pub refill_every: Duration,
+}
+
+/// Configuration of the [`UrlShortenerService`].
+///
+/// Fields missing from a deserialized [`Config`] take their
+/// [`Config::default`] values.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct Config {
+    /// Maximum length of a [`Url`] in bytes.
+    ///
+    /// Defaults to `2048`.
+    pub max_url_len: usize,
+
+    /// Indicates whether creating a short link without a custom [`Slug`]
+    /// for an already shortened [`Url`] returns the existing [`ShortLink`]
+    /// instead of creating a new one.
+    ///
+    /// Defaults to `false`.
+    pub dedupe_urls: bool,
+
+    /// Schemes a [`Url`] is allowed to have, compared case-insensitively.
+    ///
+    /// Defaults to `["http", "https"]`.
+    pub allowed_schemes: Vec<String>,
+
+    /// Maximum number of redirects a single [`ShortLink`] allows within a
+    /// sliding time window, if any.
+    ///
+    /// Defaults to [`None`].
+    pub rate_limit: Option<(u64, Duration)>,
+
+    /// Strategy of generating [`Slug`]s when no custom one is provided.
+    ///
+    /// Defaults to [`SlugStrategy::Random`].
+    pub slug_strategy: SlugStrategy,
+
+    /// Maximum number of [`Event`]s retained in the event log, if any.
+    ///
+    /// The oldest [`Event`]s are dropped once it's exceeded, while their
+    /// effect on the state is kept. Queries scanning the event log (like
+    /// [`UrlShortenerService::links_created_between`] or the
+    /// [`Config::rate_limit`] check) see only the retained [`Event`]s.
+    ///
+    /// Defaults to [`None`].
+    pub max_events: Option<usize>,
+
+    /// Maximum number of live short links (not counting aliases) the service
+    /// holds, if any. Deleting a short link frees a slot.
+    ///
+    /// Defaults to [`None`].
+    pub max_links: Option<usize>,
+
+    /// Indicates whether redirects are kept in the event log as
+    /// [`Event::LinkRedirected`]s.
+    ///
+    /// When disabled, redirects update the stats without growing the event
+    /// log, so queries scanning it (like the [`Config::rate_limit`] check)
+    /// don't see them, and the stats can't be reproduced by replaying it.
+    ///
+    /// Defaults to `true`.
+    pub track_redirect_events: bool,
+
+    /// Indicates whether a redirect of a short link whose click count has
+    /// reached [`u64::MAX`] fails with a [`ShortenerError::CounterOverflow`]
+    /// instead of leaving the click count saturated.
+    ///
+    /// Defaults to `false`.
+    pub fail_on_counter_overflow: bool,
+
+    /// Domains a [`Url`] is not allowed to point to, along with their
+    /// subdomains, compared case-insensitively.
+    ///
+    /// Defaults to none.
+    pub blocked_domains: HashSet<String>,
+
+    /// Service-wide throttle of redirects, if any, protecting from floods of
+    /// redirects regardless of the [`ShortLink`]s they target.
+    ///
+    /// Defaults to [`None`].
+    pub global_throttle: Option<GlobalThrottle>,
+
+    /// Prefix of the [`Url`]s pointing to the short links of this service
+    /// (like `https://sho.rt/`), if any, followed by
+    /// [`UrlShortenerService::handle_redirect_resolved`].
+    ///
+    /// Defaults to [`None`].
+    pub base_url: Option<String>,
+
+    /// Indicates whether the [`Url`] of a short link is fixed on its creation,
+    /// so changing it fails with a [`ShortenerError::LinkImmutable`].
+    ///
+    /// Defaults to `false`.
+    pub immutable_links: bool,
+
+    /// Time window after a counted redirect of a short link within which its
+    /// further redirects resolve the [`Url`] without being counted, if any.
+    ///
+    /// Protects the stats from being inflated by rapid double-clicks.
+    ///
+    /// Defaults to [`None`].
+    pub redirect_debounce: Option<Duration>,
+
+    /// Maximum number of characters in a custom [`Slug`] or a [`Slug`]
+    /// produced by the [`SlugGenerator`], if any. Longer ones are truncated
+    /// instead of being rejected.
+    ///
+    /// A truncated custom [`Slug`] colliding with an existing one fails with
+    /// [`ShortenerError::SlugAlreadyInUse`], while a colliding truncated
+    /// generated one is regenerated. Namespaces and the collision suffixes of
+    /// [`SlugStrategy::Hashed`] are not truncated.
+    ///
+    /// Defaults to [`None`].
+    pub truncate_slugs_to: Option<usize>,
+
+    /// Prefix prepended to the [`Slug`]s produced by the [`SlugGenerator`]
+    /// (after truncating them to [`Config::truncate_slugs_to`]), telling them
+    /// apart from the custom ones.
+    ///
+    /// Defaults to an empty string.
+    pub slug_prefix: String,
+
+    /// Base [`Url`] (like `https://app.example.com`) the relative [`Url`]s
+    /// (like `/docs/page`) are joined with on redirects, if any. Relative
+    /// [`Url`]s are accepted only if it's set.
+    ///
+    /// Defaults to [`None`].
+    pub resolve_relative: Option<String>,
+
+    /// Indicates whether creating a short link with a custom [`Slug`] already
+    /// pointing to the same [`Url`] returns the existing [`ShortLink`] instead
+    /// of failing with a [`ShortenerError::SlugAlreadyInUse`], so retries of
+    /// the creation are no-ops.
+    ///
+    /// Defaults to `false`.
+    pub idempotent_identical_create: bool,
+
+    /// Indicates whether custom [`Slug`]s which
+    /// [require percent-encoding](requires_encoding) but are allowed by the
+    /// [`SlugPolicy`] are percent-encoded on creation instead of being
+    /// rejected with a [`ShortenerError::InvalidSlug`]. Redirects accept such
+    /// [`Slug`]s both encoded and not.
+    ///
+    /// Defaults to `false`.
+    pub percent_encode_slugs: bool,
+}
+
+impl Config {
+    /// Checks whether this [`Config`] is usable by the [`UrlShortenerService`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::InvalidConfig`] if the allowed schemes are
+    /// empty, or the length slugs are truncated to is zero.
+    pub fn validate(&self) -> Result<(), ShortenerError> {
+        if self.allowed_schemes.is_empty() || self.truncate_slugs_to == Some(0) {
+            return Err(ShortenerError::InvalidConfig);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_url_len: 2048,
+            dedupe_urls: false,
+            allowed_schemes: vec!["http".into(), "https".into()],
+            rate_limit: None,
+            slug_strategy: SlugStrategy::default(),
+            max_events: None,
+            max_links: None,
+            track_redirect_events: true,
+            fail_on_counter_overflow: false,
+            blocked_domains: HashSet::new(),
+            global_throttle: None,
+            base_url: None,
+            immutable_links: false,
+            redirect_debounce: None,
+            truncate_slugs_to: None,
+            slug_prefix: String::new(),
+            resolve_relative: None,
+            idempotent_identical_create: false,
+            percent_encode_slugs: false,
+        }
+    }
+}
+
+/// Options of a short link being created by the [`UrlShortenerService`].
+#[derive(Default)]
+struct LinkOptions {
+    /// Arbitrary key-value metadata attached to the short link.
+    meta: HashMap<String, String>,
+    /// Maximum number of redirects allowed for the short link, if any.
+    max_redirects: Option<u64>,
+    /// Namespace the slug of the short link is unique within, if any.
+    namespace: Option<String>,
+    /// Idempotency key of the command creating the short link, if any.
+    idempotency_key: Option<String>,
+    /// Whether the clicks of the short link are not counted.
+    untracked: bool,
+}
+
+/// Result of the checks of a short link creation by the
+/// [`UrlShortenerService`].
+enum CreatePlan {
+    /// Return the already existing short link with this [`Slug`].
+    Reuse(Slug),
+    /// Create a new short link with this [`Slug`].
+    Create(Slug),
+    /// Create a new short link with a generated [`Slug`].
+    Generate,
+}
+
+/// Materialized state of the [`UrlShortenerService`] saved by
+/// [`UrlShortenerService::save_state`].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedState {
+    config: Config,
+    links: HashMap<Slug, Url>,
+    click_counts: HashMap<Slug, u64>,
+    redirect_limits: HashMap<Slug, u64>,
+    meta: HashMap<Slug, HashMap<String, String>>,
+    referrers: HashMap<Slug, HashMap<String, u64>>,
+    #[serde(default)]
+    countries: HashMap<Slug, HashMap<String, u64>>,
+    #[serde(default)]
+    descriptions: HashMap<Slug, String>,
+    aliases: HashMap<Slug, Slug>,
+    #[serde(default)]
+    expired: HashSet<Slug>,
+    #[serde(default)]
+    untracked: HashSet<Slug>,
+    #[serde(default)]
+    idempotency_keys: HashMap<String, ShortLink>,
+    #[serde(default)]
+    deleted_links: HashMap<Slug, Url>,
+    #[serde(default)]
+    original_urls: HashMap<Slug, Url>,
+}
+
+/// Listener notified about every [`Event`] recorded by the
+/// [`UrlShortenerService`].
+type EventListener = Box<dyn Fn(&Event) + Send + Sync>;
+
+/// Guard deciding whether a redirect by the canonical [`Slug`] to the [`Url`]
+/// is allowed.
+type RedirectGuard = Box<dyn Fn(&Slug, &Url) -> bool + Send + Sync>;
+
+/// CQRS and Event Sourcing-based service implementation
+pub struct UrlShortenerService {
+    events: Vec<Event>,
+    links: HashMap<Slug, Url>,
+    click_counts: HashMap<Slug, u64>,
+    redirect_limits: HashMap<Slug, u64>,
+    /// Slugs of the short links pointing to each [`Url`], in creation order.
+    url_index: HashMap<Url, Vec<Slug>>,
+    /// Canonical slugs of the aliases.
+    aliases: HashMap<Slug, Slug>,
+    read_model: ReadModel,
+    /// Locked only so [`UrlShortenerService::preview_random_slug`] can use it
+    /// by a shared reference.
+    slug_generator: Mutex<Box<dyn SlugGenerator + Send>>,
+    slug_policy: Box<dyn SlugPolicy + Send + Sync>,
+    clock: Box<dyn Clock + Send + Sync>,
+    on_event: Option<EventListener>,
+    redirect_guard: Option<RedirectGuard>,
+    sink: Option<Box<dyn EventSink + Send + Sync>>,
+    /// Channels every event appended to the event log is sent to.
+    subscribers: Vec<Sender<PublicEvent>>,
+    /// Reserved slugs along with the time their reservation expires at.
+    /// `None` means the reservation never expires.
+    reservations: HashMap<Slug, Option<SystemTime>>,
+    /// Slugs of the expired short links.
+    expired: HashSet<Slug>,
+    /// Slugs of the short links whose clicks are not counted.
+    untracked: HashSet<Slug>,
+    /// Short links created by the commands with idempotency keys.
+    idempotency_keys: HashMap<String, ShortLink>,
+    /// Time of the last counted redirect of each short link.
+    last_redirects: HashMap<Slug, SystemTime>,
+    /// Last [`Url`]s of the deleted short links which may be restored.
+    deleted_links: HashMap<Slug, Url>,
+    /// [`Url`]s the short links were created with, kept after deletion.
+    original_urls: HashMap<Slug, Url>,
+    /// Redirects left in the [`Config::global_throttle`] bucket along with the
+    /// time it has been refilled at. `None` means the bucket is full.
+    throttle: Option<(u64, SystemTime)>,
+    /// Number of generated slugs which turned out to be already taken.
+    slug_collisions: u64,
+    /// Number of the oldest [`Event`]s dropped from the event log.
+    trimmed_events: usize,
+    /// State of both the write and the read models before the oldest
+    /// retained [`Event`]: the one of the [`Event`]s dropped due to
+    /// [`Config::max_events`] or loaded by `load_state`. Replays of the event
+    /// log start from it.
+    base: Option<Box<UrlShortenerService>>,
+    config: Config,
+}
+
+impl Default for UrlShortenerService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UrlShortenerService {
+    /// Creates a new instance of the service
+    pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    /// Creates a new instance of the service with the provided [`Config`].
+    ///
+    /// ## Panics
+    ///
+    /// If the provided [`Config`] is invalid, see [`Config::validate`] and
+    /// [`UrlShortenerService::try_with_config`].
+    pub fn with_config(config: Config) -> Self {
+        match Self::try_with_config(config) {
+            Ok(service) => service,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    /// Creates a new instance of the service with the provided [`Config`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::InvalidConfig`] if the provided [`Config`]
+    /// is invalid, see [`Config::validate`].
+    pub fn try_with_config(config: Config) -> Result<Self, ShortenerError> {
+        config.validate()?;
+        Ok(Self {
+            events: vec![],
+            links: HashMap::new(),
+            click_counts: HashMap::new(),
+            redirect_limits: HashMap::new(),
+            url_index: HashMap::new(),
+            aliases: HashMap::new(),
+            read_model: ReadModel::new(),
+            slug_generator: Mutex::new(Box::new(RandomSlugGenerator)),
+            slug_policy: Box::new(DefaultSlugPolicy),
+            clock: Box::new(SystemClock),
+            on_event: None,
+            redirect_guard: None,
+            sink: None,
+            subscribers: Vec::new(),
+            reservations: HashMap::new(),
+            expired: HashSet::new(),
+            untracked: HashSet::new(),
+            last_redirects: HashMap::new(),
+            deleted_links: HashMap::new(),
+            original_urls: HashMap::new(),
+            idempotency_keys: HashMap::new(),
+            throttle: None,
+            slug_collisions: 0,
+            trimmed_events: 0,
+            base: None,
+            config,
+        })
+    }
+
+    /// Creates a new [`UrlShortenerService`] generating [`Slug`]s from the
+    /// provided random source instead of [`thread_rng`].
+    pub fn with_rng(rng: impl RngCore + Send + 'static) -> Self {
+        let mut service = Self::new();
+        service.set_slug_generator(RngSlugGenerator(rng));
+        service
+    }
+
+    /// Creates a new [`UrlShortenerServiceBuilder`] for configuring the
+    /// service step by step.
+    pub fn builder() -> UrlShortenerServiceBuilder {
+        UrlShortenerServiceBuilder::new()
+    }
+
+    /// Checks the provided [`Url`] against the service [`Config`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::UrlTooLong`] if the URL is longer than
+    /// [`Config::max_url_len`] bytes, or a [`ShortenerError::InvalidUrl`] if
+    /// it's not an absolute URL with one of the [`Config::allowed_schemes`].
+    fn validate_url(&self, url: &Url) -> Result<(), ShortenerError> {
+        if url.0.len() > self.config.max_url_len {
+            return Err(ShortenerError::UrlTooLong);
+        }
+        if self.config.resolve_relative.is_some() && is_relative(url) {
+            if url.0.chars().any(|c| c.is_whitespace() || c.is_control()) {
+                return Err(ShortenerError::InvalidUrl);
+            }
+            return Ok(());
+        }
+        let Some((scheme, _)) = split_scheme(&url.0) else {
+            return Err(ShortenerError::InvalidUrl);
+        };
+        let allowed = self.config.allowed_schemes
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(scheme));
+        if !allowed || url.0.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(ShortenerError::InvalidUrl);
+        }
+        if let Some(host) = normalized_host(url) {
+            let blocked = self.config.blocked_domains.iter().any(|domain| {
+                let domain = domain.to_ascii_lowercase();
+                host.strip_suffix(&domain)
+                    .is_some_and(|subdomain| subdomain.is_empty() || subdomain.ends_with('.'))
+            });
+            if blocked {
+                return Err(ShortenerError::DomainBlocked);
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks whether creating a short link with the provided [`Url`] and
+    /// optional custom [`Slug`] would succeed, without changing anything.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the [`ShortenerError`] that
+    /// [`CommandHandler::handle_create_short_link`](commands::CommandHandler::handle_create_short_link)
+    /// would return for the same arguments.
+    pub fn validate_create(&self, url: &Url, slug: Option<&Slug>) -> Result<(), ShortenerError> {
+        self.check_create(url, slug.cloned(), None, false).map(|_| ())
+    }
+
+    /// Wipes all the short links, their stats and the event log, while
+    /// preserving the [`Config`] and the [`SlugGenerator`] of the service.
+    pub fn reset(&mut self) {
+        self.events.clear();
+        self.links.clear();
+        self.click_counts.clear();
+        self.redirect_limits.clear();
+        self.url_index.clear();
+        self.aliases.clear();
+        self.reservations.clear();
+        self.expired.clear();
+        self.untracked.clear();
+        self.idempotency_keys.clear();
+        self.last_redirects.clear();
+        self.deleted_links.clear();
+        self.original_urls.clear();
+        self.throttle = None;
+        self.slug_collisions = 0;
+        self.trimmed_events = 0;
+        self.base = None;
+        self.read_model = ReadModel::new();
+    }
+
+    /// Returns the [`Event`]s recorded starting from the provided `index`.
+    ///
+    /// Consumers may remember [`UrlShortenerService::event_count`] as a cursor
+    /// and poll only the new [`Event`]s later on. An `index` past the end of
+    /// the event log results in an empty slice, while an `index` of an
+    /// [`Event`] dropped due to [`Config::max_events`] results in all the
+    /// retained ones.
+    pub fn events_since(&self, index: usize) -> &[Event] {
+        let index = index.saturating_sub(self.trimmed_events);
+        self.events.get(index..).unwrap_or_default()
+    }
+
+    /// Returns the total number of recorded [`Event`]s, including the ones
+    /// dropped due to [`Config::max_events`].
+    pub fn event_count(&self) -> usize {
+        self.trimmed_events + self.events.len()
+    }
+
+    /// Iterates over the recorded [`Event`]s along with their sequence
+    /// numbers, in the order they were recorded.
+    pub fn iter_events(&self) -> impl Iterator<Item = (usize, &Event)> {
+        self.events
+            .iter()
+            .enumerate()
+            .map(|(i, event)| (self.trimmed_events + i, event))
+    }
+
+    /// Checks the referential integrity of the event log: every [`Event`]
+    /// must refer to a [`Slug`] created (and not deleted) by the preceding
+    /// [`Event`]s.
+    ///
+    /// A log with [`Event`]s dropped due to [`Config::max_events`] may refer
+    /// to the [`Slug`]s created by the dropped ones, so it's reported as
+    /// invalid as well.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`LogError`] describing the first dangling [`Event`].
+    pub fn validate_event_log(&self) -> Result<(), LogError> {
+        validate_events(self.iter_events())
+    }
+
+    /// Replaces the event log with the provided [`Event`]s and rebuilds the
+    /// state of the service from them, like a [`UrlShortenerService::reset`]
+    /// followed by replaying them, but without notifying the event listener,
+    /// the [`EventSink`] and the subscribers.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`LogError`] describing the first dangling [`Event`] (see
+    /// [`UrlShortenerService::validate_event_log`]), indexed within the
+    /// provided ones. Nothing is changed in such case.
+    pub fn replace_event_log(&mut self, events: Vec<Event>) -> Result<(), LogError> {
+        validate_events(events.iter().enumerate())?;
+
+        self.reset();
+        for event in &events {
+            self.apply(event);
+            self.read_model.apply(event);
+        }
+        self.events = events;
+        self.trim_events();
+        Ok(())
+    }
+
+    /// Returns the last `n` retained [`Event`]s (or all of them, if there are
+    /// fewer), in the order they were recorded.
+    pub fn tail_events(&self, n: usize) -> Vec<&Event> {
+        self.events[self.events.len().saturating_sub(n)..].iter().collect()
+    }
+
+    /// Returns the retained [`Event`] at the provided `offset` from the end
+    /// of the event log, where `0` is the most recent one.
+    pub fn event_at_offset_from_end(&self, offset: usize) -> Option<&Event> {
+        self.events.iter().rev().nth(offset)
+    }
+
+    /// Iterates over the recorded [`Event`]s of the provided [`EventKind`],
+    /// in the order they were recorded.
+    pub fn events_of_type(&self, kind: EventKind) -> impl Iterator<Item = &Event> {
+        self.events.iter().filter(move |event| event.kind() == kind)
+    }
+
+    /// Returns owned copies of the recorded [`Event`]s as [`PublicEvent`]s, in
+    /// the order they were recorded.
+    pub fn export_public_events(&self) -> Vec<PublicEvent> {
+        self.events.iter().map(PublicEvent::from).collect()
+    }
+
+    /// Creates a new service by replaying the provided [`PublicEvent`]s, in
+    /// the order they are provided.
+    pub fn from_public_events(events: Vec<PublicEvent>) -> Self {
+        Self::from_events(events.into_iter().map(Event::from))
+    }
+
+    /// Creates a new service by replaying the provided [`Event`]s, in the
+    /// order they are provided.
+    pub fn from_events(events: impl IntoIterator<Item = Event>) -> Self {
+        let mut service = Self::new();
+        service.apply_events(events);
+        service
+    }
+
+    /// Creates a new service by replaying the provided [`Event`]s like
+    /// [`UrlShortenerService::from_events`], stopping at the first [`Event`]
+    /// which isn't a valid transition of the replayed state.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the index of the first invalid [`Event`] along with the
+    /// [`ShortenerError`] the corresponding command would fail with: a
+    /// [`ShortenerError::SlugNotFound`] for an [`Event`] of a missing short
+    /// link, or a [`ShortenerError::SlugAlreadyInUse`] for an [`Event`]
+    /// taking a used [`Slug`].
+    pub fn replay_strict(events: &[Event]) -> Result<Self, (usize, ShortenerError)> {
+        let mut service = Self::new();
+        for (index, event) in events.iter().enumerate() {
+            service.check_transition(event).map_err(|error| (index, error))?;
+            service.record(event.clone());
+        }
+        Ok(service)
+    }
+
+    /// Creates a new service by replaying the provided [`Event`]s like
+    /// [`UrlShortenerService::from_events`], verifying they match the
+    /// `expected` [`UrlShortenerService::events_checksum`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::ChecksumMismatch`] if the replayed
+    /// [`Event`]s have a different checksum, e.g. as they were truncated or
+    /// modified.
+    pub fn from_events_with_checksum(
+        events: impl IntoIterator<Item = Event>,
+        expected: u64,
+    ) -> Result<Self, ShortenerError> {
+        let service = Self::from_events(events);
+        if service.events_checksum() != expected {
+            return Err(ShortenerError::ChecksumMismatch);
+        }
+        Ok(service)
+    }
+
+    /// Returns the state of the service as of the [`Event`] with the provided
+    /// sequence number (see [`UrlShortenerService::iter_events`]), by
+    /// replaying the retained [`Event`]s up to and including it into a new
+    /// service with the same [`Config`].
+    ///
+    /// An `index` past the end of the event log replays all of it, while an
+    /// `index` of an [`Event`] dropped due to [`Config::max_events`] results
+    /// in the state before the oldest retained one.
+    pub fn state_at(&self, index: usize) -> UrlShortenerService {
+        let mut service = self.replay_base();
+        let events = self.iter_events().take_while(|(i, _)| *i <= index);
+        service.apply_events(events.map(|(_, event)| event.clone()));
+        service
+    }
+
+    /// Compares the current state with the one of replaying the provided
+    /// [`Event`]s into a new service with the same [`Config`], without
+    /// changing anything.
+    pub fn diff_against_replay(&self, events: &[Event]) -> ReplayDiff {
+        let mut replayed = Self::with_config(self.config.clone());
+        replayed.apply_events(events.iter().cloned());
+
+        let mut diff = ReplayDiff::default();
+        for (slug, url) in &self.links {
+            match replayed.links.get(slug) {
+                None => diff.only_current.push(slug.clone()),
+                Some(replayed_url) => {
+                    if replayed_url != url {
+                        diff.different_urls.push(slug.clone());
+                    }
+                    let count = self.click_counts.get(slug).copied().unwrap_or(0);
+                    if replayed.click_counts.get(slug).copied().unwrap_or(0) != count {
+                        diff.different_counts.push(slug.clone());
+                    }
+                }
+            }
+        }
+        diff.only_replay = replayed.links
+            .into_keys()
+            .filter(|slug| !self.links.contains_key(slug))
+            .collect();
+
+        for slugs in [
+            &mut diff.only_current,
+            &mut diff.only_replay,
+            &mut diff.different_urls,
+            &mut diff.different_counts,
+        ] {
+            slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        diff
+    }
+
+    /// Appends the provided [`Event`]s to the event log in one pass, applying
+    /// each of them to the materialized state.
+    ///
+    /// The [`Event`]s are trusted as is, without validating them like the
+    /// commands do.
+    pub fn apply_events(&mut self, events: impl IntoIterator<Item = Event>) {
+        let events = events.into_iter();
+        self.events.reserve(events.size_hint().0);
+        for event in events {
+            self.record(event);
+        }
+    }
+
+    /// Folds the recorded [`Event`]s in the order they were recorded, allowing
+    /// arbitrary aggregations over the event log.
+    pub fn fold_events<B, F>(&self, init: B, f: F) -> B
+    where
+        F: FnMut(B, &Event) -> B,
+    {
+        self.events.iter().fold(init, f)
+    }
+
+    /// Replays the recorded [`Event`]s into the provided [`EventVisitor`], in
+    /// the order they were recorded.
+    pub fn replay_into<V: EventVisitor>(&self, visitor: &mut V) {
+        for event in &self.events {
+            match event {
+                Event::LinkCreated { slug, url, .. } => visitor.on_created(slug, url),
+                Event::LinkUrlChanged { slug, url } => visitor.on_url_changed(slug, url),
+                Event::LinkRedirected { slug, .. } => visitor.on_redirected(slug),
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the [`ServiceSummary`] of this service.
+    pub fn summary(&self) -> ServiceSummary {
+        ServiceSummary {
+            link_count: self.read_model.links.len(),
+            total_events: self.event_count(),
+            total_redirects: self.read_model.click_counts.values().fold(0, |a, b| a.saturating_add(*b)),
+        }
+    }
+
+    /// Counts the short links created within the half-open `[from, to)` time
+    /// interval, according to the event log.
+    ///
+    /// Changes of existing short links are not counted.
+    pub fn links_created_between(&self, from: SystemTime, to: SystemTime) -> usize {
+        self.events
+            .iter()
+            .filter(|event| {
+                matches!(event, Event::LinkCreated { at, .. } if from <= *at && *at < to)
+            })
+            .count()
+    }
+
+    /// Returns how many times a [`Slug`] generated by the [`SlugGenerator`]
+    /// turned out to be already taken and had to be generated again.
+    ///
+    /// A growing rate of collisions indicates the generated slugs should be
+    /// longer.
+    pub fn slug_collision_count(&self) -> u64 {
+        self.slug_collisions
+    }
+
+    /// Returns up to `n` most recently created [`ShortLink`]s, newest first,
+    /// according to the event log.
+    ///
+    /// Deleted short links are not returned.
+    pub fn recent_links(&self, n: usize) -> Vec<ShortLink> {
+        let mut created: Vec<_> = self.creation_indices()
+            .into_iter()
+            .filter(|(slug, _)| self.links.contains_key(*slug))
+            .collect();
+        created.sort_by(|(_, a), (_, b)| b.cmp(a));
+        created
+            .into_iter()
+            .take(n)
+            .map(|(slug, _)| ShortLink { slug: slug.clone(), url: self.links[slug].clone() })
+            .collect()
+    }
+
+    /// Returns all the [`ShortLink`]s whose [`Url`] contains the provided
+    /// `needle` (case-sensitively), sorted by their [`Slug`]s.
+    pub fn find_by_url_contains(&self, needle: &str) -> Vec<ShortLink> {
+        let mut links: Vec<_> = self.read_model.links
+            .iter()
+            .filter(|(_, url)| url.0.contains(needle))
+            .map(|(slug, url)| ShortLink { slug: slug.clone(), url: url.clone() })
+            .collect();
+        links.sort_by(|a, b| a.slug.0.cmp(&b.slug.0));
+        links
+    }
+
+    /// Counts the distinct hosts (compared case-insensitively) the
+    /// [`ShortLink`]s point to, ignoring the [`Url`]s without one.
+    pub fn distinct_hosts(&self) -> usize {
+        self.links
+            .values()
+            .filter_map(normalized_host)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Sums up the redirect counts of the [`ShortLink`]s by the hosts
+    /// (lowercased) they point to, skipping the [`Url`]s without one.
+    pub fn redirects_by_host(&self) -> HashMap<String, u64> {
+        let mut totals = HashMap::new();
+        for (slug, url) in &self.links {
+            if let Some(host) = normalized_host(url) {
+                let total: &mut u64 = totals.entry(host).or_default();
+                *total = total.saturating_add(self.click_counts.get(slug).copied().unwrap_or(0));
+            }
+        }
+        totals
+    }
+
+    /// Returns all the never redirected [`ShortLink`]s, sorted by their
+    /// [`Slug`]s.
+    pub fn unused_links(&self) -> Vec<ShortLink> {
+        let mut links: Vec<_> = self.read_model.links
+            .iter()
+            .filter(|(slug, _)| self.read_model.click_counts.get(*slug).copied().unwrap_or(0) == 0)
+            .map(|(slug, url)| ShortLink { slug: slug.clone(), url: url.clone() })
+            .collect();
+        links.sort_by(|a, b| a.slug.0.cmp(&b.slug.0));
+        links
+    }
+
+    /// Exports the [`Stats`] of all the [`ShortLink`]s as CSV with a
+    /// `slug,url,redirects` header, sorted by their [`Slug`]s.
+    ///
+    /// Values containing commas, quotes or line breaks are quoted.
+    pub fn export_stats_csv(&self) -> String {
+        let mut links: Vec<_> = self.read_model.links.iter().collect();
+        links.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+
+        let mut csv = String::from("slug,url,redirects\n");
+        for (slug, url) in links {
+            let redirects = self.read_model.click_counts.get(slug).copied().unwrap_or(0);
+            csv.push_str(&format!("{},{},{redirects}\n", csv_field(&slug.0), csv_field(&url.0)));
+        }
+        csv
+    }
+
+    /// Iterates over the [`Stats`] of all the [`ShortLink`]s in arbitrary
+    /// order, computing them lazily one at a time.
+    pub fn iter_stats(&self) -> impl Iterator<Item = Stats> + '_ {
+        self.read_model.links.iter().map(|(slug, url)| Stats {
+            link: ShortLink { slug: slug.clone(), url: url.clone() },
+            redirects: self.read_model.click_counts.get(slug).copied().unwrap_or(0),
+        })
+    }
+
+    /// Returns the [`Distribution`] of the redirect counts across all the
+    /// [`ShortLink`]s, which is all zeros if there are none.
+    pub fn redirect_distribution(&self) -> Distribution {
+        let mut counts: Vec<_> = self.iter_stats().map(|stats| stats.redirects).collect();
+        if counts.is_empty() {
+            return Distribution::default();
+        }
+        counts.sort_unstable();
+
+        let percentile = |percent: usize| {
+            let rank = (percent * counts.len()).div_ceil(100).max(1);
+            counts[rank - 1]
+        };
+        Distribution {
+            min: counts[0],
+            max: counts[counts.len() - 1],
+            median: percentile(50),
+            p95: percentile(95),
+        }
+    }
+
+    /// Returns the [`Stats`] of all the [`ShortLink`]s redirected at least
+    /// `min` times, sorted by their redirect counts descending and then by
+    /// their [`Slug`]s.
+    pub fn stats_with_min_clicks(&self, min: u64) -> Vec<Stats> {
+        let mut stats: Vec<_> = self.iter_stats().filter(|stats| stats.redirects >= min).collect();
+        stats.sort_by(|a, b| {
+            b.redirects.cmp(&a.redirects).then_with(|| a.link.slug.0.cmp(&b.link.slug.0))
+        });
+        stats
+    }
+
+    /// Counts the [`Event::LinkCreated`] and [`Event::LinkRedirected`] events
+    /// of the provided [`Slug`] in the event log, returning them as
+    /// `(creations, redirects)`.
+    pub fn event_counts_for(&self, slug: &Slug) -> (usize, usize) {
+        let slug = self.aliases.get(slug).unwrap_or(slug);
+        self.events.iter().fold((0, 0), |(creations, redirects), event| match event {
+            Event::LinkCreated { slug: s, .. } if s == slug => (creations + 1, redirects),
+            Event::LinkRedirected { slug: s, .. } if s == slug => (creations, redirects + 1),
+            _ => (creations, redirects),
+        })
+    }
+
+    /// Returns the chronological timeline of the [`Event`]s of the
+    /// [`ShortLink`], including its renames and the aliases created for it.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if no [`Event`] of the
+    /// provided slug is recorded.
+    pub fn timeline(&self, slug: Slug) -> Result<Vec<TimelineEntry>, ShortenerError> {
+        let slug = self.aliases.get(&slug).cloned().unwrap_or(slug);
+        let timeline: Vec<_> = self.events
+            .iter()
+            .filter(|event| match event {
+                Event::SlugRenamed { old, new } => *old == slug || *new == slug,
+                Event::AliasCreated { alias, canonical } => *alias == slug || *canonical == slug,
+                Event::LinkCreated { slug: s, .. }
+                | Event::LinkUrlChanged { slug: s, .. }
+                | Event::LinkDeleted { slug: s }
+                | Event::LinkExpired { slug: s }
+                | Event::ClickTrackingChanged { slug: s, .. }
+                | Event::DescriptionSet { slug: s, .. }
+                | Event::LinkRestored { slug: s, .. }
+                | Event::RedirectsRecorded { slug: s, .. }
+                | Event::RedirectCountSet { slug: s, .. }
+                | Event::ClicksReset { slug: s }
+                | Event::LinkRedirected { slug: s, .. } => *s == slug,
+            })
+            .map(|event| TimelineEntry {
+                kind: format!("{:?}", event.kind()),
+                at: match event {
+                    Event::LinkCreated { at, .. } | Event::LinkRedirected { at, .. } => Some(*at),
+                    _ => None,
+                },
+            })
+            .collect();
+        if timeline.is_empty() {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        Ok(timeline)
+    }
+
+    /// Returns the [`Url`] the [`ShortLink`] was originally created with,
+    /// regardless of its later changes.
+    ///
+    /// The [`Url`] follows the [`Slug`] when it's renamed and is kept after
+    /// the [`ShortLink`] is deleted. If a [`Slug`] is deleted and used by a
+    /// new [`ShortLink`] later, the latest [`Event::LinkCreated`] wins rather
+    /// than the first one.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if no short link has been
+    /// created with or renamed to the provided slug.
+    pub fn original_url(&self, slug: Slug) -> Result<Url, ShortenerError> {
+        let slug = self.aliases.get(&slug).unwrap_or(&slug);
+        self.original_urls.get(slug).cloned().ok_or(ShortenerError::SlugNotFound)
+    }
+
+    /// Maps the [`Slug`]s to the index of the latest [`Event::LinkCreated`]
+    /// in the event log having created the short link, following the
+    /// [`Event::SlugRenamed`] events.
+    fn creation_indices(&self) -> HashMap<&Slug, usize> {
+        let mut created = HashMap::new();
+        for (index, event) in self.events.iter().enumerate() {
+            match event {
+                Event::LinkCreated { slug, .. } => {
+                    created.insert(slug, index);
+                }
+                Event::SlugRenamed { old, new } => {
+                    if let Some(index) = created.remove(old) {
+                        created.insert(new, index);
+                    }
+                }
+                _ => {}
+            }
+        }
+        created
+    }
+
+    /// Recomputes the click counts of both the write and the read models by
+    /// replaying the event log, repairing them if they have drifted.
+    ///
+    /// Does nothing unless [`Config::track_redirect_events`] is set, as the
+    /// redirects are not recorded in the event log otherwise.
+    pub fn rebuild_click_counts(&mut self) {
+        if !self.config.track_redirect_events {
+            return;
+        }
+        let mut replayed = self.replay_base();
+        for event in &self.events {
+            replayed.apply(event);
+        }
+        self.read_model.click_counts = replayed.click_counts.clone();
+        self.click_counts = replayed.click_counts;
+    }
+
+    /// Checks whether the materialized state of both the write and the read
+    /// models equals the one of a fresh replay of the event log.
+    ///
+    /// Meant for catching bugs of the state being mutated without recording
+    /// an [`Event`]. The replay starts from the state of the [`Event`]s
+    /// dropped due to [`Config::max_events`] or the one loaded by
+    /// `load_state`, if any. Redirects are not reproducible without
+    /// [`Config::track_redirect_events`], so they are reported as
+    /// inconsistent.
+    pub fn verify_consistency(&self) -> bool {
+        let mut replayed = self.replay_base();
+        for event in &self.events {
+            replayed.apply(event);
+            replayed.read_model.apply(event);
+        }
+
+        replayed.links == self.links
+            && replayed.click_counts == self.click_counts
+            && replayed.redirect_limits == self.redirect_limits
+            && replayed.aliases == self.aliases
+            && replayed.expired == self.expired
+            && replayed.untracked == self.untracked
+            && replayed.idempotency_keys == self.idempotency_keys
+            && replayed.deleted_links == self.deleted_links
+            && replayed.original_urls == self.original_urls
+            && replayed.read_model.links == self.read_model.links
+            && replayed.read_model.click_counts == self.read_model.click_counts
+            && replayed.read_model.aliases == self.read_model.aliases
+            && replayed.read_model.descriptions == self.read_model.descriptions
+    }
+
+    /// Returns a deterministic fingerprint of the logical state of the
+    /// service: its short links along with their redirect counts.
+    ///
+    /// Services with equal logical state have equal fingerprints, regardless
+    /// of the order their state has been built in.
+    pub fn state_fingerprint(&self) -> u64 {
+        let mut links: Vec<_> = self.read_model.links.iter().collect();
+        links.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+
+        let mut hasher = StableHasher::new();
+        for (slug, url) in links {
+            let redirects = self.read_model.click_counts.get(slug).copied().unwrap_or(0);
+            hasher.write_str(&slug.0);
+            hasher.write_str(&url.0);
+            hasher.write_u64(redirects);
+        }
+        hasher.finish()
+    }
+
+    /// Returns a deterministic checksum of the retained event log, covering
+    /// every [`Event`] along with its order, so truncated or modified logs
+    /// can be detected.
+    pub fn events_checksum(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        for event in &self.events {
+            hasher.write_event(event);
+        }
+        hasher.finish()
+    }
+
+    /// Estimates the heap memory used by the event log and the materialized
+    /// state of the service, in bytes.
+    ///
+    /// The estimate counts only the stored [`Slug`]s, [`Url`]s and strings
+    /// of metadata, so it's not exact, but grows along with the stored data.
+    pub fn estimated_memory_bytes(&self) -> usize {
+        let strings = |map: &HashMap<String, String>| -> usize {
+            map.iter().map(|(key, value)| key.len() + value.len()).sum()
+        };
+        let events: usize = self.events
+            .iter()
+            .map(|event| {
+                size_of::<Event>()
+                    + match event {
+                        Event::LinkCreated { slug, url, meta, idempotency_key, .. } => {
+                            slug.0.len()
+                                + url.0.len()
+                                + strings(meta)
+                                + idempotency_key.as_ref().map_or(0, String::len)
+                        }
+                        Event::LinkUrlChanged { slug, url }
+                        | Event::LinkRestored { slug, url } => slug.0.len() + url.0.len(),
+                        Event::DescriptionSet { slug, text } => slug.0.len() + text.len(),
+                        Event::SlugRenamed { old: a, new: b }
+                        | Event::AliasCreated { alias: a, canonical: b } => a.0.len() + b.0.len(),
+                        Event::LinkDeleted { slug }
+                        | Event::LinkExpired { slug }
+                        | Event::ClickTrackingChanged { slug, .. }
+                        | Event::RedirectsRecorded { slug, .. }
+                        | Event::RedirectCountSet { slug, .. }
+                        | Event::ClicksReset { slug } => {
+                            slug.0.len()
+                        }
+                        Event::LinkRedirected { slug, referrer, country, .. } => {
+                            slug.0.len()
+                                + referrer.as_ref().map_or(0, String::len)
+                                + country.as_ref().map_or(0, String::len)
+                        }
+                    }
+            })
+            .sum();
+        let links: usize = self.links
+            .iter()
+            .map(|(slug, url)| slug.0.len() + url.0.len())
+            .sum();
+        let click_counts: usize = self.click_counts
+            .keys()
+            .map(|slug| slug.0.len() + size_of::<u64>())
+            .sum();
+        // The read model mirrors the links and click counts of the write one.
+        events + 2 * (links + click_counts)
+    }
+
+    /// Replaces the [`SlugGenerator`] used to generate slugs for short links
+    /// created without a custom [`Slug`].
+    pub fn set_slug_generator(&mut self, generator: impl SlugGenerator + Send + 'static) {
+        self.slug_generator = Mutex::new(Box::new(generator));
+    }
+
+    /// Generates a [`Slug`] candidate with the [`SlugGenerator`] of the
+    /// service (truncated and prefixed according to the [`Config`]), without
+    /// reserving it or changing any state of the service besides the one of
+    /// the [`SlugGenerator`].
+    ///
+    /// The candidate may already be in use, or be taken before it's used.
+    pub fn preview_random_slug(&self) -> Slug {
+        let slug = self.slug_generator.lock().unwrap_or_else(PoisonError::into_inner).generate();
+        self.prefixed_slug(slug)
+    }
+
+    /// Replaces the [`SlugPolicy`] used to validate custom [`Slug`]s.
+    pub fn set_slug_policy(&mut self, policy: impl SlugPolicy + Send + Sync + 'static) {
+        self.slug_policy = Box::new(policy);
+    }
+
+    /// Replaces the [`Clock`] used by time-based features of the service.
+    pub fn set_clock(&mut self, clock: impl Clock + Send + Sync + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    /// Sets the listener notified about every recorded [`Event`], once the
+    /// state of the service has been updated with it and it has been added
+    /// to the event log.
+    ///
+    /// Untracked redirects (see [`Config::track_redirect_events`]) are not
+    /// notified about.
+    pub fn set_event_listener(&mut self, listener: impl Fn(&Event) + Send + Sync + 'static) {
+        self.on_event = Some(Box::new(listener));
+    }
+
+    /// Sets the guard consulted before every redirect with the canonical
+    /// [`Slug`] and the [`Url`] of the [`ShortLink`], allowing dynamic access
+    /// policies. Redirects it returns `false` for fail with a
+    /// [`ShortenerError::RedirectDenied`] without being counted.
+    pub fn set_redirect_guard(&mut self, guard: impl Fn(&Slug, &Url) -> bool + Send + Sync + 'static) {
+        self.redirect_guard = Some(Box::new(guard));
+    }
+
+    /// Sets the [`EventSink`] every [`Event`] appended to the event log is
+    /// persisted to from now on.
+    pub fn set_event_sink(&mut self, sink: impl EventSink + Send + Sync + 'static) {
+        self.sink = Some(Box::new(sink));
+    }
+
+    /// Subscribes to the [`Event`]s appended to the event log from now on,
+    /// which are sent to the returned channel as [`PublicEvent`]s.
+    ///
+    /// Dropping the returned [`Receiver`] unsubscribes it.
+    pub fn subscribe(&mut self) -> Receiver<PublicEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Creates a new short link with the provided metadata attached to it.
+    ///
+    /// Behaves exactly like
+    /// [`CommandHandler::handle_create_short_link`](commands::CommandHandler::handle_create_short_link),
+    /// while the metadata is recorded in the [`Event::LinkCreated`], so it
+    /// survives replaying.
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    pub fn handle_create_short_link_with_meta(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+        meta: HashMap<String, String>,
+    ) -> Result<ShortLink, ShortenerError> {
+        self.create_link(url, slug, LinkOptions { meta, ..LinkOptions::default() })
+            .map(|outcome| outcome.link)
+    }
+
+    /// Creates a new short link like
+    /// [`CommandHandler::handle_create_short_link`](commands::CommandHandler::handle_create_short_link),
+    /// reporting the offending [`Slug`] or [`Url`] on failure.
+    ///
+    /// ## Errors
+    ///
+    /// See [`DetailedError`].
+    pub fn try_create_short_link(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+    ) -> Result<ShortLink, DetailedError> {
+        let details = (slug.clone(), url.clone());
+        commands::CommandHandler::handle_create_short_link(self, url, slug)
+            .map_err(|error| DetailedError::new(error, details.0.as_ref(), Some(&details.1)))
+    }
+
+    /// Processes a redirection like
+    /// [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect),
+    /// reporting the offending [`Slug`] on failure.
+    ///
+    /// ## Errors
+    ///
+    /// See [`DetailedError`].
+    pub fn try_redirect(&mut self, slug: Slug) -> Result<ShortLink, DetailedError> {
+        commands::CommandHandler::handle_redirect(self, slug.clone())
+            .map_err(|error| DetailedError::new(error, Some(&slug), None))
+    }
+
+    /// Returns the [`Stats`] like
+    /// [`QueryHandler::get_stats`](queries::QueryHandler::get_stats),
+    /// reporting the offending [`Slug`] on failure.
+    ///
+    /// ## Errors
+    ///
+    /// See [`DetailedError`].
+    pub fn try_get_stats(&self, slug: Slug) -> Result<Stats, DetailedError> {
+        queries::QueryHandler::get_stats(self, slug.clone())
+            .map_err(|error| DetailedError::new(error, Some(&slug), None))
+    }
+
+    /// Creates a new short link like
+    /// [`CommandHandler::handle_create_short_link`](commands::CommandHandler::handle_create_short_link),
+    /// returning it along with the number of generated [`Slug`]s which turned
+    /// out to be already taken (`0` for custom [`Slug`]s).
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    pub fn handle_create_short_link_with_stats(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+    ) -> Result<(ShortLink, u32), ShortenerError> {
+        let collisions = self.slug_collisions;
+        let outcome = self.create_link(url, slug, LinkOptions::default())?;
+        let retries = u32::try_from(self.slug_collisions - collisions).unwrap_or(u32::MAX);
+        Ok((outcome.link, retries))
+    }
+
+    /// Creates a new short link like
+    /// [`CommandHandler::handle_create_short_link`](commands::CommandHandler::handle_create_short_link),
+    /// whose clicks are counted only if `track` is `true`.
+    ///
+    /// Redirects of an untracked short link are neither counted nor recorded
+    /// as [`Event`]s. An existing short link is reused only if its clicks are
+    /// counted as requested.
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    pub fn handle_create_short_link_tracked(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+        track: bool,
+    ) -> Result<ShortLink, ShortenerError> {
+        let options = LinkOptions { untracked: !track, ..LinkOptions::default() };
+        self.create_link(url, slug, options).map(|outcome| outcome.link)
+    }
+
+    /// Enables or disables counting the clicks of the [`ShortLink`], see
+    /// [`UrlShortenerService::handle_create_short_link_tracked`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn handle_set_click_tracking(&mut self, slug: Slug, track: bool) -> Result<(), ShortenerError> {
+        let slug = self.aliases.get(&slug).cloned().unwrap_or(slug);
+        if !self.links.contains_key(&slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        if self.untracked.contains(&slug) == track {
+            self.record(Event::ClickTrackingChanged { slug, track });
+        }
+        Ok(())
+    }
+
+    /// Creates a new short link like
+    /// [`CommandHandler::handle_create_short_link`](commands::CommandHandler::handle_create_short_link),
+    /// unless the [`Url`] is already shortened.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::UrlAlreadyShortened`] with the existing
+    /// [`Slug`] if the [`Url`] is already shortened. See [`ShortenerError`]
+    /// for the rest.
+    pub fn handle_create_unique_url(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+    ) -> Result<ShortLink, ShortenerError> {
+        if let Some(existing) = self.url_index.get(&url).and_then(|slugs| slugs.first()) {
+            return Err(ShortenerError::UrlAlreadyShortened(existing.clone()));
+        }
+        self.create_link(url, slug, LinkOptions::default()).map(|outcome| outcome.link)
+    }
+
+    /// Creates a new short link like
+    /// [`CommandHandler::handle_create_short_link`](commands::CommandHandler::handle_create_short_link),
+    /// unless a short link has already been created with the same
+    /// idempotency `key`, in which case the same [`ShortLink`] is returned
+    /// without creating a new one.
+    ///
+    /// The `key` is recorded in the [`Event::LinkCreated`], so it survives
+    /// replaying.
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    pub fn handle_create_idempotent(
+        &mut self,
+        key: String,
+        url: Url,
+        slug: Option<Slug>,
+    ) -> Result<ShortLink, ShortenerError> {
+        if let Some(link) = self.idempotency_keys.get(&key) {
+            return Ok(link.clone());
+        }
+        let options = LinkOptions { idempotency_key: Some(key), ..LinkOptions::default() };
+        self.create_link(url, slug, options).map(|outcome| outcome.link)
+    }
+
+    /// Creates a new short link like
+    /// [`CommandHandler::handle_create_short_link`](commands::CommandHandler::handle_create_short_link),
+    /// while its [`Slug`] is unique only within the provided namespace.
+    ///
+    /// The same [`Slug`] may be used in different namespaces. The `*_ns`
+    /// methods address short links of a namespace by the [`Slug`] within it,
+    /// while the other methods only by the scoped `{ns}/{slug}` one. The
+    /// returned [`ShortLink`] contains the [`Slug`] within the namespace.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::InvalidSlug`] if the namespace is not a
+    /// valid [`Slug`] itself. See [`ShortenerError`] for the rest.
+    pub fn handle_create_short_link_ns(
+        &mut self,
+        ns: &str,
+        url: Url,
+        slug: Option<Slug>,
+    ) -> Result<ShortLink, ShortenerError> {
+        self.validate_slug(&Slug(ns.into()))?;
+        let options = LinkOptions { namespace: Some(ns.into()), ..LinkOptions::default() };
+        let link = self.create_link(url, slug, options)?.link;
+        Ok(unscoped_link(ns, link))
+    }
+
+    /// Processes a redirection like
+    /// [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect)
+    /// by the [`Slug`] within the provided namespace.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link within the namespace.
+    pub fn handle_redirect_ns(&mut self, ns: &str, slug: Slug) -> Result<ShortLink, ShortenerError> {
+        let slug = scoped_slug(Some(ns), normalize_slug(slug));
+        let link = self.redirect(slug, None, None)?;
+        Ok(unscoped_link(ns, link))
+    }
+
+    /// Returns the [`Stats`] like
+    /// [`QueryHandler::get_stats`](queries::QueryHandler::get_stats) for the
+    /// [`Slug`] within the provided namespace.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link within the namespace.
+    pub fn get_stats_ns(&self, ns: &str, slug: Slug) -> Result<Stats, ShortenerError> {
+        let slug = scoped_slug(Some(ns), normalize_slug(slug));
+        let Stats { link, redirects } = queries::QueryHandler::get_stats(&self.read_model, slug)?;
+        Ok(Stats { link: unscoped_link(ns, link), redirects })
+    }
+
+    /// Creates a new short link like
+    /// [`CommandHandler::handle_create_short_link`](commands::CommandHandler::handle_create_short_link),
+    /// additionally reporting whether the returned [`ShortLink`] is a newly
+    /// created one or an existing one returned due to [`Config::dedupe_urls`].
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    pub fn handle_create_short_link_detailed(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+    ) -> Result<CreateOutcome, ShortenerError> {
+        self.create_link(url, slug, LinkOptions::default())
+    }
+
+    /// Creates a new short link, which stops redirecting once it has been
+    /// redirected `max_redirects` times.
+    ///
+    /// Behaves exactly like
+    /// [`CommandHandler::handle_create_short_link`](commands::CommandHandler::handle_create_short_link),
+    /// while the limit is recorded in the [`Event::LinkCreated`], so it
+    /// survives replaying.
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    pub fn handle_create_short_link_with_limit(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+        max_redirects: u64,
+    ) -> Result<ShortLink, ShortenerError> {
+        self.create_link(
+            url,
+            slug,
+            LinkOptions { max_redirects: Some(max_redirects), ..LinkOptions::default() },
+        )
+        .map(|outcome| outcome.link)
+    }
+
+    /// Renames the [`Slug`] of an existing [`ShortLink`], moving its [`Url`]
+    /// and click count to the new [`Slug`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the `old` slug does not
+    /// map to any existing short link, a [`ShortenerError::InvalidSlug`] if
+    /// the `new` slug is rejected by the [`SlugPolicy`], or a
+    /// [`ShortenerError::SlugAlreadyInUse`] if the `new` slug is already used.
+    pub fn handle_rename_slug(
+        &mut self,
+        old: Slug,
+        new: Slug,
+    ) -> Result<ShortLink, ShortenerError> {
+        let old = self.aliases.get(&old).cloned().unwrap_or(old);
+        let url = self.links.get(&old).ok_or(ShortenerError::SlugNotFound)?.clone();
+        self.validate_slug(&new)?;
+        self.remove_expired_reservations();
+        if self.is_slug_taken(&new) {
+            return Err(ShortenerError::SlugAlreadyInUse);
+        }
+
+        self.record(Event::SlugRenamed { old, new: new.clone() });
+
+        Ok(ShortLink { slug: new, url })
+    }
+
+    /// Updates the [`Url`] of an existing [`ShortLink`] like
+    /// [`CommandHandler::handle_change_short_link`](commands::CommandHandler::handle_change_short_link),
+    /// but only if it currently points to the `expected` [`Url`].
+    ///
+    /// This prevents concurrent edits from silently overwriting each other.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link, or a
+    /// [`ShortenerError::PreconditionFailed`] if its current [`Url`] is not
+    /// the `expected` one.
+    pub fn handle_change_if(
+        &mut self,
+        slug: Slug,
+        expected: Url,
+        new_url: Url,
+    ) -> Result<ShortLink, ShortenerError> {
+        let slug = self.aliases.get(&slug).cloned().unwrap_or(slug);
+        let current = self.links.get(&slug).ok_or(ShortenerError::SlugNotFound)?;
+        if *current != expected {
+            return Err(ShortenerError::PreconditionFailed);
+        }
+        commands::CommandHandler::handle_change_short_link(self, slug, new_url)
+    }
+
+    /// Creates an `alias` of the `existing` [`ShortLink`], sharing its [`Url`]
+    /// and click count: redirects by the `alias` are counted for the
+    /// `existing` [`Slug`].
+    ///
+    /// Creating an alias of an alias points it to the canonical [`Slug`].
+    ///
+    /// Every command and query of an existing [`ShortLink`] accepts its alias
+    /// in place of the canonical [`Slug`] and acts on the canonical
+    /// [`ShortLink`], except [`UrlShortenerService::handle_delete_short_link`]
+    /// deleting only the alias itself. Commands changing the [`Url`] or the
+    /// [`Slug`] return the canonical [`Slug`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the `existing` slug does
+    /// not map to any existing short link, a [`ShortenerError::InvalidSlug`]
+    /// if the `alias` is rejected by the [`SlugPolicy`], or a
+    /// [`ShortenerError::SlugAlreadyInUse`] if the `alias` is already used.
+    pub fn handle_create_alias(
+        &mut self,
+        existing: Slug,
+        alias: Slug,
+    ) -> Result<ShortLink, ShortenerError> {
+        let canonical = self.aliases.get(&existing).cloned().unwrap_or(existing);
+        let url = self.links.get(&canonical).ok_or(ShortenerError::SlugNotFound)?.clone();
+        self.validate_slug(&alias)?;
+        self.remove_expired_reservations();
+        if self.is_slug_taken(&alias) {
+            return Err(ShortenerError::SlugAlreadyInUse);
+        }
+
+        self.record(Event::AliasCreated { alias: alias.clone(), canonical });
+
+        Ok(ShortLink { slug: alias, url })
+    }
+
+    /// Deletes an existing [`ShortLink`] along with its stats and aliases,
+    /// freeing its [`Slug`].
+    ///
+    /// Deleting an alias deletes only the alias itself. The deleted
+    /// [`ShortLink`] may be brought back with [`UrlShortenerService::restore`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link or alias.
+    pub fn handle_delete_short_link(&mut self, slug: Slug) -> Result<(), ShortenerError> {
+        if !self.links.contains_key(&slug) && !self.aliases.contains_key(&slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+
+        self.record(Event::LinkDeleted { slug });
+        Ok(())
+    }
+
+    /// Swaps the [`Url`]s of two existing [`ShortLink`]s, recording an
+    /// [`Event::LinkUrlChanged`] for each of them, while their stats stay
+    /// with their [`Slug`]s.
+    ///
+    /// Swapping the [`Url`]s of aliases swaps the ones of their canonical
+    /// [`ShortLink`]s. Swapping a [`ShortLink`] with itself does nothing.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if either slug does not map
+    /// to any existing short link, or a [`ShortenerError::LinkImmutable`] if
+    /// [`Config::immutable_links`] is enabled. Nothing is changed in such
+    /// case.
+    pub fn handle_swap_urls(&mut self, a: Slug, b: Slug) -> Result<(), ShortenerError> {
+        let a = self.aliases.get(&a).cloned().unwrap_or(a);
+        let b = self.aliases.get(&b).cloned().unwrap_or(b);
+        let url_a = self.links.get(&a).ok_or(ShortenerError::SlugNotFound)?.clone();
+        let url_b = self.links.get(&b).ok_or(ShortenerError::SlugNotFound)?.clone();
+        if a == b {
+            return Ok(());
+        }
+        if self.config.immutable_links {
+            return Err(ShortenerError::LinkImmutable);
+        }
+
+        self.add_link_url_changed_event(a, url_b);
+        self.add_link_url_changed_event(b, url_a);
+        Ok(())
+    }
+
+    /// Deletes every [`ShortLink`] (along with its stats and aliases) matching
+    /// the provided predicate, which receives its [`Slug`], [`Url`] and
+    /// current redirect count.
+    ///
+    /// Returns the number of the deleted [`ShortLink`]s.
+    pub fn delete_where<F: Fn(&Slug, &Url, u64) -> bool>(&mut self, pred: F) -> usize {
+        let mut matching: Vec<_> = self.links
+            .iter()
+            .filter(|(slug, url)| pred(slug, url, self.click_counts.get(*slug).copied().unwrap_or(0)))
+            .map(|(slug, _)| slug.clone())
+            .collect();
+        matching.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let deleted = matching.len();
+        for slug in matching {
+            self.record(Event::LinkDeleted { slug });
+        }
+        deleted
+    }
+
+    /// Changes the [`Url`] of the [`ShortLink`] while rotating its [`Slug`]:
+    /// a new [`ShortLink`] with a freshly generated [`Slug`] is created for
+    /// the `new_url`, and the `old` one is deleted along with its stats and
+    /// aliases, invalidating its previous shares.
+    ///
+    /// An alias `old` slug rotates the canonical [`ShortLink`] it points to.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link, or the [`ShortenerError`] of validating
+    /// the `new_url`. Nothing is changed in such case.
+    pub fn handle_change_with_new_slug(
+        &mut self,
+        old: Slug,
+        new_url: Url,
+    ) -> Result<ShortLink, ShortenerError> {
+        let old = self.aliases.get(&old).cloned().unwrap_or(old);
+        if !self.links.contains_key(&old) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        self.validate_url(&new_url)?;
+        self.remove_expired_reservations();
+        let slug = self.generate_slug(None)?;
+
+        self.record(Event::LinkDeleted { slug: old });
+        self.add_link_created_event(slug.clone(), new_url.clone(), LinkOptions::default());
+
+        Ok(ShortLink { slug, url: new_url })
+    }
+
+    /// Restores a deleted [`ShortLink`] with the last [`Url`] it pointed to.
+    ///
+    /// Stats of the deleted [`ShortLink`] are not restored.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugAlreadyInUse`] if the provided slug is
+    /// currently used, or a [`ShortenerError::SlugNotFound`] if the last short
+    /// link with it hasn't been deleted (e.g. it has been renamed instead), or
+    /// there has never been one.
+    pub fn restore(&mut self, slug: Slug) -> Result<ShortLink, ShortenerError> {
+        self.remove_expired_reservations();
+        if self.is_slug_taken(&slug) {
+            return Err(ShortenerError::SlugAlreadyInUse);
+        }
+        let url = self.deleted_links.get(&slug).cloned().ok_or(ShortenerError::SlugNotFound)?;
+        self.check_link_limit()?;
+
+        self.record(Event::LinkRestored { slug: slug.clone(), url: url.clone() });
+
+        Ok(ShortLink { slug, url })
+    }
+
+    /// Corrects the redirect count of the [`ShortLink`] to the exact value,
+    /// recording an [`Event::RedirectCountSet`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn handle_set_redirect_count(&mut self, slug: Slug, count: u64) -> Result<(), ShortenerError> {
+        let slug = self.aliases.get(&slug).cloned().unwrap_or(slug);
+        if !self.links.contains_key(&slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        self.record(Event::RedirectCountSet { slug, count });
+        Ok(())
+    }
+
+    /// Resets the redirect count of the [`ShortLink`] to zero while keeping
+    /// the [`ShortLink`] itself, recording an [`Event::ClicksReset`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn handle_reset_clicks(&mut self, slug: Slug) -> Result<(), ShortenerError> {
+        let slug = self.aliases.get(&slug).cloned().unwrap_or(slug);
+        if !self.links.contains_key(&slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        self.record(Event::ClicksReset { slug });
+        Ok(())
+    }
+
+    /// Sets the free-text description of the [`ShortLink`], replacing the
+    /// previous one.
+    ///
+    /// Setting the description of an alias sets the one of its canonical
+    /// [`ShortLink`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn handle_set_description(&mut self, slug: Slug, text: String) -> Result<(), ShortenerError> {
+        let slug = self.aliases.get(&slug).cloned().unwrap_or(slug);
+        if !self.links.contains_key(&slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        self.record(Event::DescriptionSet { slug, text });
+        Ok(())
+    }
+
+    /// Expires the [`ShortLink`] immediately, so it no longer redirects while
+    /// its [`Stats`] remain available.
+    ///
+    /// Expiring an alias expires its canonical [`ShortLink`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn handle_expire_now(&mut self, slug: Slug) -> Result<(), ShortenerError> {
+        let slug = self.aliases.get(&slug).cloned().unwrap_or(slug);
+        if !self.links.contains_key(&slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        if !self.expired.contains(&slug) {
+            self.record(Event::LinkExpired { slug });
+        }
+        Ok(())
+    }
+
+    /// Adds the provided number of redirects to the click count of the
+    /// [`ShortLink`] at once, recording a single [`Event::RedirectsRecorded`].
+    ///
+    /// Useful for importing stats from external systems. The click count
+    /// saturates at [`u64::MAX`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn handle_record_redirects(&mut self, slug: Slug, count: u64) -> Result<(), ShortenerError> {
+        let slug = self.aliases.get(&slug).cloned().unwrap_or(slug);
+        if !self.links.contains_key(&slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        if count > 0 {
+            self.record(Event::RedirectsRecorded { slug, count });
+        }
+        Ok(())
+    }
+
+    /// Imports all the short links and aliases of the `other` service along
+    /// with their click counts and metadata.
+    ///
+    /// Short links existing in both services with the same [`Url`] have
+    /// their click counts summed up. The imported state is recorded as
+    /// [`Event::LinkCreated`] and [`Event::RedirectsRecorded`] events, while
+    /// the event history of the `other` service itself is not imported.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`DetailedError::SlugAlreadyInUse`] with the first
+    /// conflicting [`Slug`] if it's used by both services differently (or is
+    /// reserved in this service), or a [`ShortenerError::LinkLimitReached`]
+    /// if the imported short links exceed the [`Config::max_links`].
+    /// Nothing is imported in such cases.
+    pub fn merge(&mut self, other: UrlShortenerService) -> Result<(), DetailedError> {
+        self.remove_expired_reservations();
+        let mut links: Vec<_> = other.links.into_iter().collect();
+        links.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        for (slug, url) in &links {
+            let conflicts = match self.links.get(slug) {
+                Some(existing) => existing != url,
+                None => self.is_slug_taken(slug),
+            };
+            if conflicts {
+                return Err(DetailedError::SlugAlreadyInUse(slug.clone()));
+            }
+        }
+        let mut aliases: Vec<_> = other.aliases.into_iter().collect();
+        aliases.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        for (alias, canonical) in &aliases {
+            let conflicts = match self.aliases.get(alias) {
+                Some(existing) => existing != canonical,
+                None => self.is_slug_taken(alias),
+            };
+            if conflicts {
+                return Err(DetailedError::SlugAlreadyInUse(alias.clone()));
+            }
+        }
+        let new_links = links.iter().filter(|(slug, _)| !self.links.contains_key(slug)).count();
+        if let Some(max_links) = self.config.max_links {
+            if self.links.len() + new_links > max_links {
+                return Err(DetailedError::Other(ShortenerError::LinkLimitReached));
+            }
+        }
+
+        for (slug, url) in links {
+            if !self.links.contains_key(&slug) {
+                let options = LinkOptions {
+                    meta: other.read_model.meta.get(&slug).cloned().unwrap_or_default(),
+                    max_redirects: other.redirect_limits.get(&slug).copied(),
+                    untracked: other.untracked.contains(&slug),
+                    ..LinkOptions::default()
+                };
+                self.add_link_created_event(slug.clone(), url, options);
+            }
+            let count = other.click_counts.get(&slug).copied().unwrap_or(0);
+            if count > 0 {
+                self.record(Event::RedirectsRecorded { slug: slug.clone(), count });
+            }
+            if other.untracked.contains(&slug) && !self.untracked.contains(&slug) {
+                self.record(Event::ClickTrackingChanged { slug: slug.clone(), track: false });
+            }
+            if let Some(text) = other.read_model.descriptions.get(&slug) {
+                if self.read_model.descriptions.get(&slug) != Some(text) {
+                    let text = text.clone();
+                    self.record(Event::DescriptionSet { slug: slug.clone(), text });
+                }
+            }
+            if other.expired.contains(&slug) && !self.expired.contains(&slug) {
+                self.record(Event::LinkExpired { slug });
+            }
+        }
+
+        for (alias, canonical) in aliases {
+            if !self.aliases.contains_key(&alias) {
+                self.record(Event::AliasCreated { alias, canonical });
+            }
+        }
+        Ok(())
+    }
+
+    /// Processes a redirection by [`Slug`] like
+    /// [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect),
+    /// merging the `incoming_query` into the query string of the returned
+    /// [`Url`].
+    ///
+    /// Parameters of the `incoming_query` override the ones of the stored
+    /// [`Url`] having the same key.
+    ///
+    /// ## Errors
+    ///
+    /// See [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect).
+    pub fn handle_redirect_with_query(
+        &mut self,
+        slug: Slug,
+        incoming_query: &str,
+    ) -> Result<ShortLink, ShortenerError> {
+        let ShortLink { slug, url } = commands::CommandHandler::handle_redirect(self, slug)?;
+        Ok(ShortLink { slug, url: Url(merge_query(&url.0, incoming_query)) })
+    }
+
+    /// Processes a redirection by [`Slug`] like
+    /// [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect),
+    /// recording the `referrer` the redirect came from.
+    ///
+    /// ## Errors
+    ///
+    /// See [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect).
+    pub fn handle_redirect_with_referrer(
+        &mut self,
+        slug: Slug,
+        referrer: Option<String>,
+    ) -> Result<ShortLink, ShortenerError> {
+        self.redirect(slug, referrer, None)
+    }
+
+    /// Processes a redirection by [`Slug`] like
+    /// [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect),
+    /// following up to `max_hops` short links of this service (see
+    /// [`Config::base_url`]) it redirects to, and counting a redirect for each
+    /// of them.
+    ///
+    /// Returns the last [`ShortLink`] of the chain, pointing to an external
+    /// [`Url`].
+    ///
+    /// ## Errors
+    ///
+    /// [`ShortenerError::RedirectLoop`] if the chain revisits a short link or
+    /// is longer than `max_hops`.
+    ///
+    /// See [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect)
+    /// for others. No redirect is counted if any short link of the chain
+    /// fails.
+    pub fn handle_redirect_resolved(
+        &mut self,
+        slug: Slug,
+        max_hops: usize,
+    ) -> Result<ShortLink, ShortenerError> {
+        let mut chain = vec![normalize_slug(slug)];
+        let mut visited = HashSet::new();
+        loop {
+            let slug = chain.last().expect("chain is never empty");
+            let canonical = self.aliases.get(slug).unwrap_or(slug);
+            if !visited.insert(canonical.clone()) {
+                return Err(ShortenerError::RedirectLoop);
+            }
+            let url = self.links.get(canonical).ok_or(ShortenerError::SlugNotFound)?;
+            let Some(next) = self.internal_slug(url) else {
+                break;
+            };
+            if chain.len() > max_hops {
+                return Err(ShortenerError::RedirectLoop);
+            }
+            chain.push(next);
+        }
+
+        let hops = chain
+            .into_iter()
+            .map(|slug| self.check_redirect(slug))
+            .collect::<Result<Vec<_>, _>>()?;
+        let throttle = self.throttle;
+        if !hops.iter().all(|_| self.take_throttle_token()) {
+            self.throttle = throttle;
+            return Err(ShortenerError::RateLimited);
+        }
+
+        let mut link = None;
+        for (hop, counted) in hops {
+            if let Some(canonical) = counted {
+                self.add_link_redirected_event(&canonical, None, None);
+            }
+            link = Some(hop);
+        }
+        Ok(link.expect("chain is never empty"))
+    }
+
+    /// Returns the [`Slug`] of the existing short link of this service the
+    /// provided [`Url`] points to, if any.
+    fn internal_slug(&self, url: &Url) -> Option<Slug> {
+        let base_url = self.config.base_url.as_deref()?;
+        let slug = Slug(url.0.strip_prefix(base_url)?.trim_end_matches('/').to_owned());
+        (self.links.contains_key(&slug) || self.aliases.contains_key(&slug)).then_some(slug)
+    }
+
+    /// Processes a redirection by [`Slug`] like
+    /// [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect),
+    /// recording the code of the `country` the redirect came from.
+    ///
+    /// ## Errors
+    ///
+    /// See [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect).
+    pub fn handle_redirect_with_country(
+        &mut self,
+        slug: Slug,
+        country: Option<String>,
+    ) -> Result<ShortLink, ShortenerError> {
+        self.redirect(slug, None, country)
+    }
+
+    /// Reserves the provided [`Slug`] for the `ttl` duration, so it can't be
+    /// used by anyone until the reservation is committed with
+    /// [`UrlShortenerService::commit_reserved`] or expires.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugAlreadyInUse`] if the provided slug is
+    /// already used by a short link or has a live reservation.
+    pub fn reserve(&mut self, slug: Slug, ttl: Duration) -> Result<(), ShortenerError> {
+        self.validate_slug(&slug)?;
+        self.remove_expired_reservations();
+        if self.is_slug_taken(&slug) {
+            return Err(ShortenerError::SlugAlreadyInUse);
+        }
+
+        let expires_at = self.clock.now().checked_add(ttl);
+        self.reservations.insert(slug, expires_at);
+        Ok(())
+    }
+
+    /// Turns a live reservation of the provided [`Slug`] into a new short
+    /// link pointing to the provided [`Url`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug has no
+    /// live reservation. See [`ShortenerError`] for other errors.
+    pub fn commit_reserved(&mut self, slug: Slug, url: Url) -> Result<ShortLink, ShortenerError> {
+        self.remove_expired_reservations();
+        if !self.reservations.contains_key(&slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        self.validate_url(&url)?;
+
+        let expires_at = self.reservations.remove(&slug).flatten();
+        let result = self.create_link(url, Some(slug.clone()), LinkOptions::default());
+        if result.is_err() {
+            self.reservations.insert(slug, expires_at);
+        }
+        result.map(|outcome| outcome.link)
+    }
+
+    /// Returns the [`ShortLink`] of the provided [`Slug`] without counting a
+    /// redirect, unlike
+    /// [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect).
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn resolve(&self, slug: Slug) -> Result<ShortLink, ShortenerError> {
+        self.read_model.resolve(normalize_slug(slug))
+    }
+
+    /// Returns the [`ShortLink`] along with its [`Stats`].
+    ///
+    /// Same as [`QueryHandler::get_stats`](queries::QueryHandler::get_stats),
+    /// as the returned [`Stats`] contain the [`ShortLink`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn get_link_with_stats(&self, slug: Slug) -> Result<Stats, ShortenerError> {
+        queries::QueryHandler::get_stats(self, slug)
+    }
+
+    /// Returns the metadata attached to the [`ShortLink`] on its creation.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn get_meta(&self, slug: Slug) -> Result<HashMap<String, String>, ShortenerError> {
+        self.read_model.get_meta(slug)
+    }
+
+    /// Returns the histogram of the referrers the redirects of the
+    /// [`ShortLink`] came from.
+    ///
+    /// Redirects without a known referrer are counted under the
+    /// [`DIRECT_REFERRER`] key.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn referrers(&self, slug: Slug) -> Result<HashMap<String, u64>, ShortenerError> {
+        self.read_model.referrers(slug)
+    }
+
+    /// Returns the histogram of the countries the redirects of the
+    /// [`ShortLink`] came from.
+    ///
+    /// Redirects without a known country are counted under the
+    /// [`UNKNOWN_COUNTRY`] key.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn country_breakdown(&self, slug: Slug) -> Result<HashMap<String, u64>, ShortenerError> {
+        self.read_model.country_breakdown(slug)
+    }
+
+    /// Returns the description of the [`ShortLink`], if it's set.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn get_description(&self, slug: Slug) -> Result<Option<String>, ShortenerError> {
+        self.read_model.get_description(slug)
+    }
+
+    /// Returns an iterator over every [`ShortLink`] [`Slug`] and its number
+    /// of redirects, including never redirected ones.
+    pub fn redirect_iter(&self) -> impl Iterator<Item = (&Slug, u64)> {
+        self.read_model.redirect_iter()
+    }
+
+    /// Returns the [`Stats`] of the [`ShortLink`] along with its metadata.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugNotFound`] if the provided slug does not
+    /// map to any existing short link.
+    pub fn get_stats_with_meta(
+        &self,
+        slug: Slug,
+    ) -> Result<(Stats, HashMap<String, String>), ShortenerError> {
+        let meta = self.read_model.get_meta(slug.clone())?;
+        let stats = queries::QueryHandler::get_stats(self, slug)?;
+        Ok((stats, meta))
+    }
+
+    /// Creates a new short link with the provided [`LinkOptions`].
+    ///
+    /// If [`Config::dedupe_urls`] is set and no custom [`Slug`] is provided,
+    /// returns the existing [`ShortLink`] of the [`Url`], if any.
+    fn create_link(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+        options: LinkOptions,
+    ) -> Result<CreateOutcome, ShortenerError> {
+        self.remove_expired_reservations();
+        let namespace = options.namespace.as_deref();
+        let slug = match self.check_create(&url, slug, namespace, options.untracked)? {
+            CreatePlan::Reuse(slug) => {
+                return Ok(CreateOutcome { link: ShortLink { slug, url }, created: false });
+            }
+            CreatePlan::Create(slug) => slug,
+            CreatePlan::Generate => self.generate_slug(namespace)?,
+        };
+
+        self.add_link_created_event(slug.clone(), url.clone(), options);
+
+        Ok(CreateOutcome { link: ShortLink { slug, url }, created: true })
+    }
+
+    /// Runs all the checks of creating a short link with the provided [`Url`]
+    /// and optional custom [`Slug`] within the provided namespace, without
+    /// changing anything.
+    ///
+    /// Only the short links whose clicks are counted as requested by
+    /// `untracked` are reused.
+    ///
+    /// ## Errors
+    ///
+    /// See [`ShortenerError`].
+    fn check_create(
+        &self,
+        url: &Url,
+        slug: Option<Slug>,
+        namespace: Option<&str>,
+        untracked: bool,
+    ) -> Result<CreatePlan, ShortenerError> {
+        self.validate_url(url)?;
+        let reusable = |slug: &Slug| {
+            self.links.get(slug) == Some(url) && self.untracked.contains(slug) == untracked
+        };
+
+        if slug.is_none() && self.config.dedupe_urls {
+            let existing = self.url_index.get(url).and_then(|slugs| {
+                slugs.iter().find(|slug| {
+                    let in_namespace = match namespace {
+                        Some(ns) => unscoped_slug(ns, slug).is_some(),
+                        None => !slug.0.contains(NAMESPACE_SEPARATOR),
+                    };
+                    in_namespace && reusable(slug)
+                })
+            });
+            if let Some(existing) = existing {
+                return Ok(CreatePlan::Reuse(existing.clone()));
+            }
+        }
+
+        let plan = match slug {
+            Some(custom_slug) => {
+                let custom_slug = self.truncate_slug(normalize_slug(custom_slug));
+                let custom_slug = scoped_slug(namespace, self.encode_slug(custom_slug)?);
+                if self.config.idempotent_identical_create && reusable(&custom_slug) {
+                    return Ok(CreatePlan::Reuse(custom_slug));
+                }
+                if self.is_slug_taken(&custom_slug) {
+                    return Err(ShortenerError::SlugAlreadyInUse)
+                }
+                CreatePlan::Create(custom_slug)
+            }
+            None if self.config.slug_strategy == SlugStrategy::Hashed => {
+                let hashed_slug = scoped_slug(namespace, hashed_slug(url));
+                let mut slug = hashed_slug.clone();
+                let mut suffix = 0;
+                loop {
+                    if reusable(&slug) {
+                        return Ok(CreatePlan::Reuse(slug));
+                    }
+                    if !self.is_slug_taken(&slug) {
+                        break CreatePlan::Create(slug);
+                    }
+                    suffix += 1;
+                    slug = Slug(format!("{}-{suffix}", hashed_slug.0));
+                }
+            }
+            None => CreatePlan::Generate,
+        };
+
+        self.check_link_limit()?;
+        Ok(plan)
+    }
+
+    /// Generates a new [`Slug`] within the provided namespace which is not
+    /// taken yet, counting the collisions.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::SlugSpaceExhausted`] if no free [`Slug`]
+    /// is generated within [`MAX_SLUG_ATTEMPTS`] attempts.
+    fn generate_slug(&mut self, namespace: Option<&str>) -> Result<Slug, ShortenerError> {
+        for _ in 0..MAX_SLUG_ATTEMPTS {
+            let generated_slug = self.slug_generator
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .generate();
+            let generated_slug = scoped_slug(namespace, self.prefixed_slug(generated_slug));
+            if !self.is_slug_taken(&generated_slug) {
+                return Ok(generated_slug);
+            }
+            self.slug_collisions += 1;
+        }
+        Err(ShortenerError::SlugSpaceExhausted)
+    }
+
+    /// Truncates the provided generated [`Slug`] and prepends the
+    /// [`Config::slug_prefix`] to it.
+    fn prefixed_slug(&self, slug: Slug) -> Slug {
+        let slug = self.truncate_slug(slug);
+        if self.config.slug_prefix.is_empty() {
+            return slug;
+        }
+        Slug(format!("{}{}", self.config.slug_prefix, slug.0))
+    }
+
+    /// Truncates the provided [`Slug`] to [`Config::truncate_slugs_to`]
+    /// characters.
+    fn truncate_slug(&self, slug: Slug) -> Slug {
+        match self.config.truncate_slugs_to {
+            Some(max_len) if slug.0.chars().count() > max_len => {
+                Slug(slug.0.chars().take(max_len).collect())
+            }
+            _ => slug,
+        }
+    }
+
+    /// Checks whether the provided [`Event`] is a valid transition of the
+    /// current state, see [`UrlShortenerService::replay_strict`].
+    fn check_transition(&self, event: &Event) -> Result<(), ShortenerError> {
+        let taken = |slug| self.links.contains_key(slug) || self.aliases.contains_key(slug);
+        let (missing, used) = match event {
+            Event::LinkCreated { slug, .. } | Event::LinkRestored { slug, .. } => {
+                (None, Some(slug))
+            }
+            Event::AliasCreated { alias: new, canonical: old }
+            | Event::SlugRenamed { old, new } => (Some(old), Some(new)),
+            Event::LinkDeleted { slug } if !taken(slug) => {
+                return Err(ShortenerError::SlugNotFound);
+            }
+            Event::LinkDeleted { .. } => (None, None),
+            Event::LinkUrlChanged { slug, .. }
+            | Event::LinkExpired { slug }
+            | Event::ClickTrackingChanged { slug, .. }
+            | Event::DescriptionSet { slug, .. }
+            | Event::RedirectsRecorded { slug, .. }
+            | Event::RedirectCountSet { slug, .. }
+            | Event::ClicksReset { slug }
+            | Event::LinkRedirected { slug, .. } => (Some(slug), None),
+        };
+        if missing.is_some_and(|slug| !self.links.contains_key(slug)) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        if used.is_some_and(taken) {
+            return Err(ShortenerError::SlugAlreadyInUse);
+        }
+        Ok(())
+    }
+
+    /// Checks whether one more short link fits into [`Config::max_links`].
+    fn check_link_limit(&self) -> Result<(), ShortenerError> {
+        match self.config.max_links {
+            Some(max_links) if self.links.len() >= max_links => {
+                Err(ShortenerError::LinkLimitReached)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Processes a redirection by [`Slug`] coming from the provided referrer.
+    ///
+    /// Redirects by an alias are counted for its canonical [`Slug`].
+    fn redirect(
+        &mut self,
+        slug: Slug,
+        referrer: Option<String>,
+        country: Option<String>,
+    ) -> Result<ShortLink, ShortenerError> {
+        if !self.take_throttle_token() {
+            return Err(ShortenerError::RateLimited);
+        }
+        let (link, counted) = self.check_redirect(slug)?;
+        if let Some(canonical) = counted {
+            self.add_link_redirected_event(&canonical, referrer, country);
+        }
+        Ok(link)
+    }
+
+    /// Runs all the checks of a redirection by [`Slug`] except the
+    /// [`Config::global_throttle`], without changing anything.
+    ///
+    /// Returns the [`ShortLink`] to redirect to along with the canonical
+    /// [`Slug`] to count the redirect for, if it should be counted.
+    ///
+    /// ## Errors
+    ///
+    /// See [`CommandHandler::handle_redirect`](commands::CommandHandler::handle_redirect).
+    fn check_redirect(&self, slug: Slug) -> Result<(ShortLink, Option<Slug>), ShortenerError> {
+        let mut slug = normalize_slug(slug);
+        if self.config.percent_encode_slugs
+            && !self.links.contains_key(&slug)
+            && !self.aliases.contains_key(&slug)
+        {
+            slug = Slug(percent_encode(&slug.0));
+        }
+        let canonical = self.aliases.get(&slug).unwrap_or(&slug).clone();
+        let url = self.links.get(&canonical).ok_or(ShortenerError::SlugNotFound)?.clone();
+        if self.redirect_guard.as_ref().is_some_and(|guard| !guard(&canonical, &url)) {
+            return Err(ShortenerError::RedirectDenied);
+        }
+        if self.expired.contains(&canonical) {
+            return Err(ShortenerError::LinkExpired);
+        }
+        if self.untracked.contains(&canonical) || self.is_debounced(&canonical) {
+            return Ok((ShortLink { slug, url: self.absolute_url(url) }, None));
+        }
+        if let Some(limit) = self.redirect_limits.get(&canonical) {
+            if self.click_counts.get(&canonical).unwrap_or(&0) >= limit {
+                return Err(ShortenerError::RedirectLimitReached);
+            }
+        }
+        if self.config.fail_on_counter_overflow && self.click_counts.get(&canonical) == Some(&u64::MAX) {
+            return Err(ShortenerError::CounterOverflow);
+        }
+        if let Some((max_redirects, window)) = self.config.rate_limit {
+            if self.recent_redirects(&canonical, window) >= max_redirects {
+                return Err(ShortenerError::RateLimited);
+            }
+        }
+
+        Ok((ShortLink { slug, url: self.absolute_url(url) }, Some(canonical)))
+    }
+
+    /// Joins the provided relative [`Url`] with the
+    /// [`Config::resolve_relative`] base, if it's set.
+    fn absolute_url(&self, url: Url) -> Url {
+        match &self.config.resolve_relative {
+            Some(base) if is_relative(&url) => {
+                Url(format!("{}{}", base.trim_end_matches('/'), url.0))
+            }
+            _ => url,
+        }
+    }
+
+    /// Checks whether the last counted redirect of the provided [`Slug`] is
+    /// within the [`Config::redirect_debounce`] window.
+    fn is_debounced(&self, slug: &Slug) -> bool {
+        let (Some(window), Some(last)) = (self.config.redirect_debounce, self.last_redirects.get(slug))
+        else {
+            return false;
+        };
+        self.clock.now().duration_since(*last).is_ok_and(|elapsed| elapsed < window)
+    }
+
+    /// Takes a single redirect from the [`Config::global_throttle`] bucket
+    /// after refilling it, returning whether it wasn't empty.
+    fn take_throttle_token(&mut self) -> bool {
+        let Some(GlobalThrottle { capacity, refill_every }) = self.config.global_throttle else {
+            return true;
+        };
+        let now = self.clock.now();
+        let (mut tokens, mut refilled_at) = self.throttle.unwrap_or((capacity, now));
+
+        let elapsed = now.duration_since(refilled_at).unwrap_or_default();
+        let nanos = refill_every.as_nanos().max(1);
+        let refills = u64::try_from(elapsed.as_nanos() / nanos).unwrap_or(u64::MAX);
+        if tokens.saturating_add(refills) >= capacity {
+            (tokens, refilled_at) = (capacity, now);
+        } else {
+            // Keep the time passed since the last refill for the next one.
+            let remainder = u64::try_from(elapsed.as_nanos() % nanos).unwrap_or(u64::MAX);
+            tokens += refills;
+            refilled_at = now - Duration::from_nanos(remainder).min(elapsed);
+        }
+
+        let taken = tokens > 0;
+        self.throttle = Some((tokens.saturating_sub(1), refilled_at));
+        taken
+    }
+
+    /// Counts the redirects of the provided [`Slug`] which occurred within the
+    /// last `window` of time.
+    fn recent_redirects(&self, slug: &Slug, window: Duration) -> u64 {
+        let now = self.clock.now();
+        let since = now.checked_sub(window).unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut count = 0;
+        // Redirects are recorded in chronological order, so the scan stops at
+        // the first redirect which is out of the window.
+        for event in self.events.iter().rev() {
+            if let Event::LinkRedirected { slug: redirected, at, .. } = event {
+                if *at <= since {
+                    break;
+                }
+                if redirected == slug {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Checks the provided custom [`Slug`] against the [`SlugPolicy`].
+    ///
+    /// Slugs which [require percent-encoding](requires_encoding) are rejected
+    /// regardless of the [`SlugPolicy`], as they break when used as a path
+    /// segment of a short URL.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::InvalidSlug`] if the slug is rejected.
+    fn validate_slug(&self, slug: &Slug) -> Result<(), ShortenerError> {
+        if !self.slug_policy.is_valid(&slug.0) || requires_encoding(&slug.0) {
+            return Err(ShortenerError::InvalidSlug);
+        }
+        Ok(())
+    }
+
+    /// Checks the provided custom [`Slug`] like
+    /// [`UrlShortenerService::validate_slug`], percent-encoding it instead of
+    /// rejecting if it [requires encoding](requires_encoding) and
+    /// [`Config::percent_encode_slugs`] is set.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::InvalidSlug`] if the slug is rejected.
+    fn encode_slug(&self, slug: Slug) -> Result<Slug, ShortenerError> {
+        if !self.config.percent_encode_slugs || !requires_encoding(&slug.0) {
+            self.validate_slug(&slug)?;
+            return Ok(slug);
+        }
+        if !self.slug_policy.is_valid(&slug.0) {
+            return Err(ShortenerError::InvalidSlug);
+        }
+        Ok(Slug(percent_encode(&slug.0)))
+    }
+
+    /// Checks whether the provided [`Slug`] is used by a short link or an
+    /// alias, or has a live reservation.
+    fn is_slug_taken(&self, slug: &Slug) -> bool {
+        let now = self.clock.now();
+        self.links.contains_key(slug)
+            || self.aliases.contains_key(slug)
+            || self.reservations
+                .get(slug)
+                .is_some_and(|expires_at| expires_at.is_none_or(|expires_at| now < expires_at))
+    }
+
+    /// Frees the slugs whose reservation has expired.
+    fn remove_expired_reservations(&mut self) {
+        let now = self.clock.now();
+        self.reservations
+            .retain(|_, expires_at| expires_at.is_none_or(|expires_at| now < expires_at));
+    }
+
+    /// Creates a LinkCreated event and adds it to the list of events
+    fn add_link_created_event(&mut self, slug: Slug, url: Url, options: LinkOptions) {
+        let LinkOptions { meta, max_redirects, idempotency_key, untracked, .. } = options;
+        let at = self.clock.now();
+        let event = Event::LinkCreated { slug, url, meta, max_redirects, idempotency_key, untracked, at };
+        self.record(event);
+    }
+
+    /// Creates a LinkUrlChanged event and adds it to the list of events
+    fn add_link_url_changed_event(&mut self, slug: Slug, url: Url) {
+        self.record(Event::LinkUrlChanged { slug, url });
+    }
+
+    /// Creates a LinkRedirected event and adds it to the list of events
+    fn add_link_redirected_event(
+        &mut self,
+        slug: &Slug,
+        referrer: Option<String>,
+        country: Option<String>,
+    ) {
+        let at = self.clock.now();
+        self.record(Event::LinkRedirected { slug: slug.clone(), referrer, country, at });
+    }
+
+    /// Applies the [`Event`] to both the write and the read models and adds
+    /// it to the list of events (unless it's an untracked redirect, see
+    /// [`Config::track_redirect_events`]), notifying the event listener,
+    /// persisting it to the [`EventSink`] and sending it to the subscribers.
+    fn record(&mut self, event: Event) {
+        self.apply(&event);
+        self.read_model.apply(&event);
+        if self.config.track_redirect_events || !matches!(event, Event::LinkRedirected { .. }) {
+            let public = (!self.subscribers.is_empty() || self.sink.is_some())
+                .then(|| PublicEvent::from(&event));
+            self.events.push(event);
+            if let (Some(listener), Some(event)) = (&self.on_event, self.events.last()) {
+                listener(event);
+            }
+            self.trim_events();
+            if let Some(public) = public {
+                if let Some(sink) = &mut self.sink {
+                    sink.persist(&public);
+                }
+                self.subscribers.retain(|subscriber| subscriber.send(public.clone()).is_ok());
+            }
+        }
+    }
+
+    /// Drops the oldest [`Event`]s exceeding [`Config::max_events`], applying
+    /// them to the [base](UrlShortenerService::base) state.
+    fn trim_events(&mut self) {
+        let Some(max_events) = self.config.max_events else {
+            return;
+        };
+        let Some(excess) = self.events.len().checked_sub(max_events) else {
+            return;
+        };
+        let config = &self.config;
+        let base = self.base.get_or_insert_with(|| Box::new(Self::with_config(config.clone())));
+        for event in self.events.drain(..excess) {
+            base.apply(&event);
+            base.read_model.apply(&event);
+        }
+        self.trimmed_events += excess;
+    }
+
+    /// Creates a new service with the same [`Config`] and the
+    /// [base](UrlShortenerService::base) state, which the retained [`Event`]s
+    /// are replayed into.
+    fn replay_base(&self) -> Self {
+        let Some(base) = &self.base else {
+            return Self::with_config(self.config.clone());
+        };
+        let mut service = base.clone_state();
+        service.base = Some(Box::new(base.clone_state()));
+        service.trimmed_events = self.trimmed_events;
+        service
+    }
+
+    /// Creates a new service with the same [`Config`] and a copy of the state
+    /// of both the write and the read models, without the event log.
+    fn clone_state(&self) -> Self {
+        let mut service = Self::with_config(self.config.clone());
+        service.links = self.links.clone();
+        service.click_counts = self.click_counts.clone();
+        service.redirect_limits = self.redirect_limits.clone();
+        service.url_index = self.url_index.clone();
+        service.aliases = self.aliases.clone();
+        service.read_model = self.read_model.clone();
+        service.expired = self.expired.clone();
+        service.untracked = self.untracked.clone();
+        service.idempotency_keys = self.idempotency_keys.clone();
+        service.last_redirects = self.last_redirects.clone();
+        service.deleted_links = self.deleted_links.clone();
+        service.original_urls = self.original_urls.clone();
+        service
+    }
+
+    /// Removes the provided [`Slug`] from the reverse index of the [`Url`].
+    fn unindex_url(&mut self, url: &Url, slug: &Slug) {
+        if let Some(slugs) = self.url_index.get_mut(url) {
+            slugs.retain(|indexed| indexed != slug);
+            if slugs.is_empty() {
+                self.url_index.remove(url);
+            }
+        }
+    }
+
+    /// Applies the [`Event`] to the write model.
+    fn apply(&mut self, event: &Event) {
+        match event {
+            Event::LinkCreated { slug, url, max_redirects, idempotency_key, untracked, .. } => {
+                self.deleted_links.remove(slug);
+                if *untracked {
+                    self.untracked.insert(slug.clone());
+                }
+                self.original_urls.insert(slug.clone(), url.clone());
+                self.links.insert(slug.clone(), url.clone());
+                self.url_index.entry(url.clone()).or_default().push(slug.clone());
+                if let Some(max_redirects) = max_redirects {
+                    self.redirect_limits.insert(slug.clone(), *max_redirects);
+                }
+                if let Some(key) = idempotency_key {
+                    let link = ShortLink { slug: slug.clone(), url: url.clone() };
+                    self.idempotency_keys.insert(key.clone(), link);
+                }
+            }
+            Event::LinkUrlChanged { slug, url } | Event::LinkRestored { slug, url } => {
+                self.deleted_links.remove(slug);
+                if let Some(old_url) = self.links.insert(slug.clone(), url.clone()) {
+                    self.unindex_url(&old_url, slug);
+                }
+                self.url_index.entry(url.clone()).or_default().push(slug.clone());
+            }
+            Event::AliasCreated { alias, canonical } => {
+                self.aliases.insert(alias.clone(), canonical.clone());
+            }
+            Event::LinkDeleted { slug } => {
+                if self.aliases.remove(slug).is_none() {
+                    if let Some(url) = self.links.remove(slug) {
+                        self.unindex_url(&url, slug);
+                        self.deleted_links.insert(slug.clone(), url);
+                    }
+                    self.click_counts.remove(slug);
+                    self.redirect_limits.remove(slug);
+                    self.expired.remove(slug);
+                    self.untracked.remove(slug);
+                    self.last_redirects.remove(slug);
+                    self.aliases.retain(|_, canonical| canonical != slug);
+                    self.idempotency_keys.retain(|_, link| link.slug != *slug);
+                }
+            }
+            Event::LinkExpired { slug } => {
+                self.expired.insert(slug.clone());
+            }
+            Event::DescriptionSet { .. } => {}
+            Event::ClickTrackingChanged { slug, track } => {
+                if *track {
+                    self.untracked.remove(slug);
+                } else {
+                    self.untracked.insert(slug.clone());
+                }
+            }
+            Event::SlugRenamed { old, new } => {
+                self.deleted_links.remove(new);
+                if let Some(url) = self.original_urls.remove(old) {
+                    self.original_urls.insert(new.clone(), url);
+                }
+                if let Some(url) = self.links.remove(old) {
+                    let indexed = self.url_index.get_mut(&url).into_iter().flatten();
+                    for slug in indexed.filter(|slug| *slug == old) {
+                        *slug = new.clone();
+                    }
+                    self.links.insert(new.clone(), url);
+                }
+                if let Some(count) = self.click_counts.remove(old) {
+                    self.click_counts.insert(new.clone(), count);
+                }
+                if let Some(limit) = self.redirect_limits.remove(old) {
+                    self.redirect_limits.insert(new.clone(), limit);
+                }
+                if self.expired.remove(old) {
+                    self.expired.insert(new.clone());
+                }
+                if self.untracked.remove(old) {
+                    self.untracked.insert(new.clone());
+                }
+                if let Some(at) = self.last_redirects.remove(old) {
+                    self.last_redirects.insert(new.clone(), at);
+                }
+                for canonical in self.aliases.values_mut().filter(|canonical| *canonical == old) {
+                    *canonical = new.clone();
+                }
+                for link in self.idempotency_keys.values_mut().filter(|link| link.slug == *old) {
+                    link.slug = new.clone();
+                }
+            }
+            Event::RedirectsRecorded { slug, count } => {
+                let total = self.click_counts.entry(slug.clone()).or_insert(0);
+                *total = total.saturating_add(*count);
+            }
+            Event::RedirectCountSet { slug, count } => {
+                self.click_counts.insert(slug.clone(), *count);
+            }
+            Event::ClicksReset { slug } => {
+                self.click_counts.insert(slug.clone(), 0);
+            }
+            Event::LinkRedirected { slug, at, .. } => {
+                let total = self.click_counts.entry(slug.clone()).or_insert(0);
+                *total = total.saturating_add(1);
+                self.last_redirects.insert(slug.clone(), *at);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl UrlShortenerService {
+    /// Serializes the event log to newline-delimited JSON, one [`Event`] per
+    /// line, so it can be streamed to external tools.
+    pub fn export_events_jsonl(&self) -> String {
+        self.events
+            .iter()
+            .map(|event| {
+                let mut line = serde_json::to_string(event).expect("serializing event never fails");
+                line.push('\n');
+                line
+            })
+            .collect()
+    }
+
+    /// Creates a new service by replaying the [`Event`]s in the
+    /// newline-delimited JSON produced by
+    /// [`UrlShortenerService::export_events_jsonl`].
+    ///
+    /// Blank lines are skipped.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an [`ImportError`] with the number of the first line which is
+    /// not a valid [`Event`].
+    pub fn import_events_jsonl(jsonl: &str) -> Result<Self, ImportError> {
+        let events = jsonl
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|_| ImportError { line: index + 1 })
+            })
+            .collect::<Result<Vec<Event>, _>>()?;
+        Ok(Self::from_events(events))
+    }
+
+    /// Serializes the materialized state of the service (its short links,
+    /// their stats and the [`Config`]) to JSON.
+    ///
+    /// Unlike replaying the event log, loading the saved state with
+    /// [`UrlShortenerService::load_state`] is instant, but the event history
+    /// is lost: the loaded service starts with an empty event log.
+    pub fn save_state(&self) -> String {
+        let state = SavedState {
+            config: self.config.clone(),
+            links: self.links.clone(),
+            click_counts: self.click_counts.clone(),
+            redirect_limits: self.redirect_limits.clone(),
+            meta: self.read_model.meta.clone(),
+            referrers: self.read_model.referrers.clone(),
+            countries: self.read_model.countries.clone(),
+            descriptions: self.read_model.descriptions.clone(),
+            aliases: self.aliases.clone(),
+            expired: self.expired.clone(),
+            untracked: self.untracked.clone(),
+            idempotency_keys: self.idempotency_keys.clone(),
+            deleted_links: self.deleted_links.clone(),
+            original_urls: self.original_urls.clone(),
+        };
+        serde_json::to_string(&state).expect("serializing state never fails")
+    }
+
+    /// Loads the service from the JSON produced by
+    /// [`UrlShortenerService::save_state`], with an empty event log.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::InvalidState`] if the provided JSON is not
+    /// a valid saved state.
+    pub fn load_state(json: &str) -> Result<Self, ShortenerError> {
+        let state: SavedState =
+            serde_json::from_str(json).map_err(|_| ShortenerError::InvalidState)?;
+
+        let mut service =
+            Self::try_with_config(state.config).map_err(|_| ShortenerError::InvalidState)?;
+        let mut slugs: Vec<_> = state.links.keys().cloned().collect();
+        slugs.sort_by(|a, b| a.0.cmp(&b.0));
+        for slug in slugs {
+            service.url_index.entry(state.links[&slug].clone()).or_default().push(slug);
+        }
+        service.read_model = ReadModel {
+            links: state.links.clone(),
+            click_counts: state.click_counts.clone(),
+            meta: state.meta,
+            referrers: state.referrers,
+            countries: state.countries,
+            descriptions: state.descriptions,
+            aliases: state.aliases.clone(),
+        };
+        service.aliases = state.aliases;
+        service.links = state.links;
+        service.click_counts = state.click_counts;
+        service.redirect_limits = state.redirect_limits;
+        service.expired = state.expired;
+        service.untracked = state.untracked;
+        service.idempotency_keys = state.idempotency_keys;
+        service.deleted_links = state.deleted_links;
+        service.original_urls = state.original_urls;
+        service.base = Some(Box::new(service.clone_state()));
+        Ok(service)
+    }
+}
+
+/// Builder of a configured [`UrlShortenerService`].
+///
+/// Options not set explicitly take their [`Config::default`] values.
+#[derive(Clone, Debug, Default)]
+pub struct UrlShortenerServiceBuilder {
+    config: Config,
+    slug_length: Option<usize>,
+    slug_alphabet: Option<Vec<char>>,
+}
+
+impl UrlShortenerServiceBuilder {
+    /// Creates a new [`UrlShortenerServiceBuilder`] with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the [`Config::max_url_len`].
+    pub fn max_url_len(mut self, max_url_len: usize) -> Self {
+        self.config.max_url_len = max_url_len;
+        self
+    }
+
+    /// Sets the [`Config::dedupe_urls`].
+    pub fn dedupe_urls(mut self, dedupe_urls: bool) -> Self {
+        self.config.dedupe_urls = dedupe_urls;
+        self
+    }
+
+    /// Sets the [`Config::allowed_schemes`].
+    pub fn allowed_schemes<S: Into<String>>(mut self, schemes: impl IntoIterator<Item = S>) -> Self {
+        self.config.allowed_schemes = schemes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the [`Config::rate_limit`].
+    pub fn rate_limit(mut self, max_redirects: u64, window: Duration) -> Self {
+        self.config.rate_limit = Some((max_redirects, window));
+        self
+    }
+
+    /// Sets the [`Config::slug_strategy`].
+    pub fn slug_strategy(mut self, strategy: SlugStrategy) -> Self {
+        self.config.slug_strategy = strategy;
+        self
+    }
+
+    /// Sets the [`Config::max_events`].
+    pub fn max_events(mut self, max_events: usize) -> Self {
+        self.config.max_events = Some(max_events);
+        self
+    }
+
+    /// Sets the [`Config::track_redirect_events`].
+    pub fn track_redirect_events(mut self, track: bool) -> Self {
+        self.config.track_redirect_events = track;
+        self
+    }
+
+    /// Sets the [`Config::fail_on_counter_overflow`].
+    pub fn fail_on_counter_overflow(mut self, fail: bool) -> Self {
+        self.config.fail_on_counter_overflow = fail;
+        self
+    }
+
+    /// Sets the [`Config::blocked_domains`].
+    pub fn blocked_domains<S: Into<String>>(mut self, domains: impl IntoIterator<Item = S>) -> Self {
+        self.config.blocked_domains = domains.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the [`Config::global_throttle`].
+    pub fn global_throttle(mut self, capacity: u64, refill_every: Duration) -> Self {
+        self.config.global_throttle = Some(GlobalThrottle { capacity, refill_every });
+        self
+    }
+
+    /// Sets the [`Config::base_url`].
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.config.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Sets the [`Config::immutable_links`].
+    pub fn immutable_links(mut self, immutable_links: bool) -> Self {
+        self.config.immutable_links = immutable_links;
+        self
+    }
+
+    /// Sets the [`Config::redirect_debounce`].
+    pub fn redirect_debounce(mut self, window: Duration) -> Self {
+        self.config.redirect_debounce = Some(window);
+        self
+    }
+
+    /// Sets the [`Config::truncate_slugs_to`].
+    pub fn truncate_slugs_to(mut self, max_len: usize) -> Self {
+        self.config.truncate_slugs_to = Some(max_len);
+        self
+    }
+
+    /// Sets the [`Config::slug_prefix`].
+    pub fn slug_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.config.slug_prefix = prefix.into();
+        self
+    }
+
+    /// Sets the [`Config::resolve_relative`].
+    pub fn resolve_relative(mut self, base: impl Into<String>) -> Self {
+        self.config.resolve_relative = Some(base.into());
+        self
+    }
+
+    /// Sets the [`Config::idempotent_identical_create`].
+    pub fn idempotent_identical_create(mut self, idempotent_identical_create: bool) -> Self {
+        self.config.idempotent_identical_create = idempotent_identical_create;
+        self
+    }
+
+    /// Sets the [`Config::percent_encode_slugs`].
+    pub fn percent_encode_slugs(mut self, percent_encode_slugs: bool) -> Self {
+        self.config.percent_encode_slugs = percent_encode_slugs;
+        self
+    }
+
+    /// Sets the [`Config::max_links`].
+    pub fn max_links(mut self, max_links: usize) -> Self {
+        self.config.max_links = Some(max_links);
+        self
+    }
+
+    /// Sets the length of the randomly generated [`Slug`]s.
+    ///
+    /// Defaults to `8`.
+    pub fn slug_length(mut self, length: usize) -> Self {
+        self.slug_length = Some(length);
+        self
+    }
+
+    /// Sets the characters the randomly generated [`Slug`]s consist of.
+    ///
+    /// Defaults to ASCII letters and digits.
+    pub fn slug_alphabet(mut self, alphabet: &str) -> Self {
+        self.slug_alphabet = Some(alphabet.chars().collect());
+        self
+    }
+
+    /// Builds the configured [`UrlShortenerService`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ShortenerError::InvalidConfig`] if the slug alphabet is
+    /// empty, the slug length is zero, or the [`Config`] is invalid (see
+    /// [`Config::validate`]).
+    pub fn build(self) -> Result<UrlShortenerService, ShortenerError> {
+        if self.slug_alphabet.as_ref().is_some_and(Vec::is_empty) || self.slug_length == Some(0) {
+            return Err(ShortenerError::InvalidConfig);
+        }
+
+        let mut service = UrlShortenerService::try_with_config(self.config)?;
+        if self.slug_length.is_some() || self.slug_alphabet.is_some() {
+            let alphabet = self.slug_alphabet.unwrap_or_else(|| {
+                ('A'..='Z').chain('a'..='z').chain('0'..='9').collect()
+            });
+            let length = self.slug_length.unwrap_or(8);
+            service.set_slug_generator(AlphabetSlugGenerator { alphabet, length });
+        }
+        Ok(service)
+    }
+}
+
+impl commands::CommandHandler for UrlShortenerService {
+    fn handle_create_short_link(
+        &mut self,
+        url: Url,
+        slug: Option<Slug>,
+    ) -> Result<ShortLink, ShortenerError> {
+        self.handle_create_short_link_with_meta(url, slug, HashMap::new())
+    }
+
+    fn handle_redirect(
+        &mut self,
+        slug: Slug,
+    ) -> Result<ShortLink, ShortenerError> {
+        self.redirect(slug, None, None)
+    }
+
+    fn handle_change_short_link(
+        &mut self,
+        slug: Slug,
+        new_url: Url,
+    ) -> Result<ShortLink, ShortenerError> {
+        let slug = self.aliases.get(&slug).cloned().unwrap_or(slug);
+        if !self.links.contains_key(&slug) {
+            return Err(ShortenerError::SlugNotFound);
+        }
+        if self.config.immutable_links {
+            return Err(ShortenerError::LinkImmutable);
+        }
+        self.validate_url(&new_url)?;
+
+        self.add_link_url_changed_event(slug.clone(), new_url.clone());
+
+        Ok(ShortLink { slug, url: new_url })
+    }
+}
+
+impl queries::QueryHandler for UrlShortenerService {
+    fn get_stats(&self, slug: Slug) -> Result<Stats, ShortenerError> {
+        self.read_model.get_stats(normalize_slug(slug))
+    }
+}
+
+/// Checks the referential integrity of the provided [`Event`]s along with
+/// their sequence numbers, see [`UrlShortenerService::validate_event_log`].
+fn validate_events<'a>(
+    events: impl IntoIterator<Item = (usize, &'a Event)>,
+) -> Result<(), LogError> {
+    let mut live = HashSet::new();
+    for (index, event) in events {
+        let dangling = match event {
+            Event::LinkCreated { slug, .. } | Event::LinkRestored { slug, .. } => {
+                live.insert(slug);
+                false
+            }
+            Event::AliasCreated { alias, canonical } => {
+                live.insert(alias);
+                !live.contains(canonical)
+            }
+            Event::SlugRenamed { old, new } => {
+                live.insert(new);
+                !live.remove(old)
+            }
+            Event::LinkDeleted { slug } => !live.remove(slug),
+            Event::LinkUrlChanged { slug, .. }
+            | Event::LinkExpired { slug }
+            | Event::ClickTrackingChanged { slug, .. }
+            | Event::DescriptionSet { slug, .. }
+            | Event::RedirectsRecorded { slug, .. }
+            | Event::RedirectCountSet { slug, .. }
+            | Event::ClicksReset { slug }
+            | Event::LinkRedirected { slug, .. } => !live.contains(slug),
+        };
+        if dangling {
+            return Err(LogError { index, kind: event.kind() });
+        }
+    }
+    Ok(())
+}
+
+/// Strips leading and trailing whitespace of the provided [`Slug`], as it's
+/// often pasted along with it.
+fn normalize_slug(slug: Slug) -> Slug {
+    match slug.0.trim() {
+        trimmed if trimmed.len() == slug.0.len() => slug,
+        trimmed => Slug(trimmed.to_owned()),
+    }
+}
+
+/// Separator of the namespace and the [`Slug`] within it in a scoped
+/// [`Slug`].
+const NAMESPACE_SEPARATOR: char = '/';
+
+/// Scopes the provided [`Slug`] to the namespace, if any.
+///
+/// As the separator is not a valid [`Slug`] character, scoped [`Slug`]s never
+/// collide with the unscoped ones.
+fn scoped_slug(namespace: Option<&str>, slug: Slug) -> Slug {
+    match namespace {
+        Some(ns) => Slug(format!("{ns}{NAMESPACE_SEPARATOR}{}", slug.0)),
+        None => slug,
+    }
+}
+
+/// Returns the [`Slug`] within the provided namespace, if the scoped [`Slug`]
+/// belongs to it.
+fn unscoped_slug<'s>(namespace: &str, slug: &'s Slug) -> Option<&'s str> {
+    slug.0.strip_prefix(namespace)?.strip_prefix(NAMESPACE_SEPARATOR)
+}
+
+/// Replaces the scoped [`Slug`] of the [`ShortLink`] with the one within the
+/// provided namespace.
+fn unscoped_link(namespace: &str, link: ShortLink) -> ShortLink {
+    match unscoped_slug(namespace, &link.slug) {
+        Some(slug) => ShortLink { slug: Slug(slug.into()), url: link.url },
+        None => link,
+    }
+}
+
+/// 64-bit FNV-1a hasher, producing the same hashes across platforms and
+/// Rust versions, unlike [`std::hash::DefaultHasher`].
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Writes the string prefixed with its length, so adjacent strings can't
+    /// be confused.
+    fn write_str(&mut self, s: &str) {
+        self.write_u64(s.len() as u64);
+        self.write(s.as_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_opt_str(&mut self, s: Option<&str>) {
+        match s {
+            Some(s) => {
+                self.write(&[1]);
+                self.write_str(s);
+            }
+            None => self.write(&[0]),
+        }
+    }
+
+    fn write_opt_u64(&mut self, n: Option<u64>) {
+        match n {
+            Some(n) => {
+                self.write(&[1]);
+                self.write_u64(n);
+            }
+            None => self.write(&[0]),
+        }
+    }
+
+    fn write_time(&mut self, time: SystemTime) {
+        let since_epoch = time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        self.write_u64(since_epoch.as_secs());
+        self.write_u64(u64::from(since_epoch.subsec_nanos()));
+    }
+
+    /// Writes all the fields of the [`Event`] prefixed with its kind.
+    fn write_event(&mut self, event: &Event) {
+        self.write_str(&format!("{:?}", event.kind()));
+        match event {
+            Event::LinkCreated { slug, url, meta, max_redirects, idempotency_key, untracked, at } => {
+                self.write_str(&slug.0);
+                self.write_str(&url.0);
+                let mut meta: Vec<_> = meta.iter().collect();
+                meta.sort();
+                self.write_u64(meta.len() as u64);
+                for (key, value) in meta {
+                    self.write_str(key);
+                    self.write_str(value);
+                }
+                self.write_opt_u64(*max_redirects);
+                self.write_opt_str(idempotency_key.as_deref());
+                self.write_u64(u64::from(*untracked));
+                self.write_time(*at);
+            }
+            Event::LinkUrlChanged { slug, url } | Event::LinkRestored { slug, url } => {
+                self.write_str(&slug.0);
+                self.write_str(&url.0);
+            }
+            Event::SlugRenamed { old: a, new: b }
+            | Event::AliasCreated { alias: a, canonical: b } => {
+                self.write_str(&a.0);
+                self.write_str(&b.0);
+            }
+            Event::LinkDeleted { slug }
+            | Event::LinkExpired { slug }
+            | Event::ClicksReset { slug } => {
+                self.write_str(&slug.0);
+            }
+            Event::ClickTrackingChanged { slug, track } => {
+                self.write_str(&slug.0);
+                self.write(&[u8::from(*track)]);
+            }
+            Event::DescriptionSet { slug, text } => {
+                self.write_str(&slug.0);
+                self.write_str(text);
+            }
+            Event::RedirectsRecorded { slug, count } | Event::RedirectCountSet { slug, count } => {
+                self.write_str(&slug.0);
+                self.write_u64(*count);
+            }
+            Event::LinkRedirected { slug, referrer, country, at } => {
+                self.write_str(&slug.0);
+                self.write_opt_str(referrer.as_deref());
+                self.write_opt_str(country.as_deref());
+                self.write_time(*at);
+            }
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Escapes the provided value as a CSV field, quoting it if needed.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Derives an 8 character base62 [`Slug`] from the hash of the provided
+/// [`Url`].
+fn hashed_slug(url: &Url) -> Slug {
+    const ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+    let mut hasher = StableHasher::new();
+    hasher.write_str(&url.0);
+    let mut hash = hasher.finish();
+
+    let slug = (0..8)
+        .map(|_| {
+            let c = ALPHABET[(hash % 62) as usize];
+            hash /= 62;
+            char::from(c)
+        })
+        .collect();
+    Slug(slug)
+}
+
+/// Splits the provided URL into its scheme and the rest following the `:`.
+///
+/// Returns [`None`] if the URL has no valid scheme or nothing follows it.
+fn split_scheme(url: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = url.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid_scheme = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    let valid_rest = match rest.strip_prefix("//") {
+        Some(authority) => !authority.is_empty() && !authority.starts_with('/'),
+        None => !rest.is_empty(),
+    };
+    (valid_scheme && valid_rest).then_some((scheme, rest))
+}
+
+/// Checks whether the provided [`Url`] is a path relative to the host, like
+/// `/docs/page`.
+fn is_relative(url: &Url) -> bool {
+    url.0.starts_with('/') && !url.0.starts_with("//")
+}
+
+/// Extracts the host of the provided URL, if it has an authority.
+fn host_of(url: &str) -> Option<&str> {
+    let (_, rest) = split_scheme(url)?;
+    let authority = rest.strip_prefix("//")?;
+    let authority = authority
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if !host.starts_with('[') || host.ends_with(']') => {
+            port.chars().all(|c| c.is_ascii_digit()).then_some(host)?
+        }
+        _ => host,
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+/// Extracts the lowercased host of the provided [`Url`] without a trailing
+/// dot, if it has an authority.
+fn normalized_host(url: &Url) -> Option<String> {
+    host_of(&url.0).map(|host| host.trim_end_matches('.').to_ascii_lowercase())
+}
+
+/// Checks whether the provided slug contains characters which must be
+/// percent-encoded to be used as a URL path segment.
+///
+/// Only unreserved URL characters (ASCII letters, digits, `-`, `.`, `_` and
+/// `~`) are allowed to be used as is.
+pub fn requires_encoding(slug: &str) -> bool {
+    !slug.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~'))
+}
+
+/// Percent-encodes every byte of the provided slug except the unreserved URL
+/// characters, see [`requires_encoding`].
+fn percent_encode(slug: &str) -> String {
+    let mut encoded = String::with_capacity(slug.len());
+    for byte in slug.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Merges the `incoming` query string into the query string of the `url`.
+///
+/// Parameters of the `incoming` query override the ones of the `url` having
+/// the same key, while the fragment of the `url` is kept at its end.
+fn merge_query(url: &str, incoming: &str) -> String {
+    let incoming = incoming.trim_start_matches('?');
+    if incoming.is_empty() {
+        return url.to_owned();
+    }
+
+    let (url, fragment) = match url.split_once('#') {
+        Some((url, fragment)) => (url, Some(fragment)),
+        None => (url, None),
+    };
+    let (base, query) = url.split_once('?').unwrap_or((url, ""));
+    let key = |pair: &str| pair.split_once('=').map_or(pair, |(key, _)| key).to_owned();
+
+    let mut pairs: Vec<&str> = query.split('&').filter(|pair| !pair.is_empty()).collect();
+    for pair in incoming.split('&').filter(|pair| !pair.is_empty()) {
+        match pairs.iter().position(|existing| key(existing) == key(pair)) {
+            Some(i) => pairs[i] = pair,
+            None => pairs.push(pair),
+        }
+    }
+
+    let mut merged = format!("{base}?{}", pairs.join("&"));
+    if let Some(fragment) = fragment {
+        merged.push('#');
+        merged.push_str(fragment);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandHandler;
+    use crate::queries::QueryHandler;
+    use std::sync::{Arc, Mutex};
+
+    /// [`Clock`] whose time is advanced manually, shared between its clones.
+    #[derive(Clone)]
+    struct MockClock(Arc<Mutex<SystemTime>>);
+
+    impl MockClock {
+        fn new() -> Self {
+            Self(Arc::new(Mutex::new(SystemTime::UNIX_EPOCH)))
+        }
+
+        fn set(&self, now: SystemTime) {
+            *self.0.lock().unwrap() = now;
+        }
+
+        fn advance(&self, duration: Duration) {
+            *self.0.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> SystemTime {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    /// [`SlugGenerator`] emitting a predefined sequence of slugs.
+    struct SequenceSlugGenerator(std::vec::IntoIter<&'static str>);
+
+    impl SequenceSlugGenerator {
+        fn new(slugs: Vec<&'static str>) -> Self {
+            Self(slugs.into_iter())
+        }
+    }
+
+    impl SlugGenerator for SequenceSlugGenerator {
+        fn generate(&mut self) -> Slug {
+            Slug(self.0.next().expect("slug sequence exhausted").into())
+        }
+    }
+
+    #[test]
+    fn test_random_slug_generation_creates_unique_link() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF8".to_string());
+
+        let result = service.handle_create_short_link(url.clone(), None);
+        assert!(result.is_ok());
+
+        let short_link = result.unwrap();
+        assert_eq!(short_link.url, url);
+        assert_eq!(service.links.len(), 1);
+    }
+
+    #[test]
+    fn test_custom_slug_allows_exact_alias_creation() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF8".to_string());
+        let slug = Slug("my_slug".to_string());
+
+        let result = service.handle_create_short_link(url.clone(), Some(slug.clone()));
+        assert!(result.is_ok());
+
+        let short_link = result.unwrap();
+        assert_eq!(short_link.slug, slug);
+        assert_eq!(short_link.url, url);
+        assert_eq!(service.links.len(), 1);
+    }
+
+    #[test]
+    fn test_duplicate_slug_creation_should_fail() {
+        let mut service = UrlShortenerService::new();
+        let url1 = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF8".to_string());
+        let slug = Slug("my_slug".to_string());
+
+        service.handle_create_short_link(url1.clone(), Some(slug.clone())).unwrap();
+
+        let url2 = Url("https://www.amazon.com/Redragon-Keyboard-Wireless-Independent-Multimedia/dp/B0CTMMN857/ref=pd_ci_mcx_pspc_dp_2_i_2?pd_rd_w=J8fu0".to_string());
+        let result = service.handle_create_short_link(url2, Some(slug));
+        assert!(matches!(result, Err(ShortenerError::SlugAlreadyInUse)));
+    }
+
+    #[test]
+    fn test_redirect_increases_click_count_for_existing_slug() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF".to_string());
+        let slug = Slug("my_slug".to_string());
+
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+
+        let result = service.handle_redirect(slug.clone());
+        assert!(result.is_ok());
+
+        let short_link = result.unwrap();
+        assert_eq!(short_link.url, url);
+        assert_eq!(short_link.slug, slug);
+        assert_eq!(service.click_counts.get(&slug), Some(&1));
+    }
+
+    #[test]
+    fn test_redirect_fails_for_nonexistent_slug() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("slug_does_not_exist".to_string());
+
+        let result = service.handle_redirect(slug);
+        assert!(matches!(result, Err(ShortenerError::SlugNotFound)));
+    }
+
+    #[test]
+    fn test_stats_retrieval_reflects_actual_clicks_for_valid_slug() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF".to_string());
+        let slug = Slug("my_slug".to_string());
+
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+
+        let result = service.get_stats(slug.clone());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert_eq!(stats.link.url, url);
+        assert_eq!(stats.redirects, 2);
+    }
+
+    #[test]
+    fn test_stats_retrieval_fails_gracefully_for_missing_slug() {
+        let service = UrlShortenerService::new();
+        let slug = Slug("slug_does_not_exist".to_string());
+
+        let result = service.get_stats(slug);
+        assert!(matches!(result, Err(ShortenerError::SlugNotFound)));
+    }
+
+    #[test]
+    fn test_change_short_link_success() {
+        let mut service = UrlShortenerService::new();
+        let original_url = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF8".into());
+        let new_url = Url("https://www.amazon.com/Redragon-Keyboard-Wireless-Independent-Multimedia/dp/B0CTMMN857/ref=pd_ci_mcx_pspc_dp_2_i_2?pd_rd_w=J8fu0".into());
+        let slug = Slug("my_slug".into());
+
+        // Create the initial short link
+        service.handle_create_short_link(original_url.clone(), Some(slug.clone())).unwrap();
+
+        // Change the short link's destination URL
+        let result = service.handle_change_short_link(slug.clone(), new_url.clone());
+
+        assert!(result.is_ok());
+        let updated_short_link = result.unwrap();
+        assert_eq!(updated_short_link.url, new_url);
+        assert_eq!(updated_short_link.slug, slug);
+
+        // Verify that the internal state reflects the change
+        let stats = service.get_stats(slug.clone()).unwrap();
+        assert_eq!(stats.link.url, new_url);
+    }
+
+    #[test]
+    fn test_change_short_link_slug_not_found() {
+        let mut service = UrlShortenerService::new();
+        let new_url = Url("https://www.amazon.com/Redragon-Keyboard-Wireless-Independent-Multimedia/dp/B0CTMMN857/ref=pd_ci_mcx_pspc_dp_2_i_2?pd_rd_w=J8fu0".into());
+        let slug = Slug("slug_does_not_exist".into());
+
+        // Attempt to change a non-existent short link
+        let result = service.handle_change_short_link(slug.clone(), new_url.clone());
+
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), ShortenerError::SlugNotFound);
+    }
+
+    #[test]
+    fn test_url_at_max_len_is_accepted() {
+        let mut service = UrlShortenerService::with_config(Config { max_url_len: 32, ..Config::default() });
+        let url = Url(format!("https://example.com/{}", "a".repeat(12)));
+        assert_eq!(url.0.len(), 32);
+
+        let result = service.handle_create_short_link(url.clone(), None);
+        assert_eq!(result.unwrap().url, url);
+    }
+
+    #[test]
+    fn test_url_just_over_max_len_is_rejected() {
+        let mut service = UrlShortenerService::with_config(Config { max_url_len: 32, ..Config::default() });
+        let url = Url(format!("https://example.com/{}", "a".repeat(13)));
+        assert_eq!(url.0.len(), 33);
+
+        let result = service.handle_create_short_link(url, None);
+        assert_eq!(result.unwrap_err(), ShortenerError::UrlTooLong);
+        assert!(service.links.is_empty());
+    }
+
+    #[test]
+    fn test_url_max_len_counts_bytes_not_chars() {
+        let mut service = UrlShortenerService::with_config(Config { max_url_len: 32, ..Config::default() });
+        // 12 two-byte chars: 32 chars would fit, but 44 bytes do not.
+        let url = Url(format!("https://example.com/{}", "é".repeat(12)));
+        assert_eq!(url.0.chars().count(), 32);
+
+        let result = service.handle_create_short_link(url, None);
+        assert_eq!(result.unwrap_err(), ShortenerError::UrlTooLong);
+    }
+
+    #[test]
+    fn test_change_short_link_rejects_too_long_url() {
+        let mut service = UrlShortenerService::with_config(Config { max_url_len: 32, ..Config::default() });
+        let url = Url("https://example.com/".into());
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+
+        let new_url = Url(format!("https://example.com/{}", "a".repeat(13)));
+        let result = service.handle_change_short_link(slug.clone(), new_url);
+        assert_eq!(result.unwrap_err(), ShortenerError::UrlTooLong);
+        assert_eq!(service.get_stats(slug).unwrap().link.url, url);
+    }
+
+    #[test]
+    fn test_standalone_read_model_matches_service_stats() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let other = Slug("other".into());
+        service.handle_create_short_link(Url("https://example.com/a".into()), Some(slug.clone())).unwrap();
+        service.handle_create_short_link(Url("https://example.com/b".into()), Some(other.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_change_short_link(other.clone(), Url("https://example.com/c".into())).unwrap();
+
+        let mut read_model = ReadModel::new();
+        for event in &service.events {
+            read_model.apply(event);
+        }
+
+        assert_eq!(read_model.get_stats(slug.clone()), service.get_stats(slug));
+        assert_eq!(read_model.get_stats(other.clone()), service.get_stats(other));
+        assert_eq!(
+            read_model.get_stats(Slug("missing".into())),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn test_injected_slug_generator_is_used_and_retried_on_collision() {
+        let mut service = UrlShortenerService::new();
+        service.set_slug_generator(SequenceSlugGenerator::new(vec!["first", "first", "taken", "second"]));
+        service.handle_create_short_link(Url("https://example.com/taken".into()), Some(Slug("taken".into()))).unwrap();
+
+        let first = service.handle_create_short_link(Url("https://example.com/1".into()), None).unwrap();
+        let second = service.handle_create_short_link(Url("https://example.com/2".into()), None).unwrap();
+
+        assert_eq!(first.slug, Slug("first".into()));
+        assert_eq!(second.slug, Slug("second".into()));
+    }
+
+    #[test]
+    fn test_create_short_link_with_meta_stores_tags() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let meta = HashMap::from([
+            ("campaign".to_string(), "black_friday".to_string()),
+            ("channel".to_string(), "email".to_string()),
+        ]);
+
+        service
+            .handle_create_short_link_with_meta(Url("https://example.com".into()), Some(slug.clone()), meta.clone())
+            .unwrap();
+        service.handle_change_short_link(slug.clone(), Url("https://example.com/new".into())).unwrap();
+
+        assert_eq!(service.get_meta(slug.clone()), Ok(meta.clone()));
+        let (stats, stats_meta) = service.get_stats_with_meta(slug).unwrap();
+        assert_eq!(stats.link.url, Url("https://example.com/new".into()));
+        assert_eq!(stats_meta, meta);
+    }
+
+    #[test]
+    fn test_link_without_meta_returns_empty_map() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+
+        assert_eq!(service.get_meta(slug), Ok(HashMap::new()));
+        assert_eq!(service.get_meta(Slug("missing".into())), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_reset_wipes_state_but_keeps_config() {
+        let mut service = UrlShortenerService::with_config(Config { max_url_len: 32, ..Config::default() });
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+
+        service.reset();
+
+        assert!(service.events.is_empty());
+        assert!(service.links.is_empty());
+        assert!(service.click_counts.is_empty());
+        assert_eq!(service.get_stats(slug.clone()), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.handle_redirect(slug), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.config.max_url_len, 32);
+        let too_long = Url(format!("https://example.com/{}", "a".repeat(13)));
+        assert_eq!(service.handle_create_short_link(too_long, None), Err(ShortenerError::UrlTooLong));
+    }
+
+    #[test]
+    fn test_rename_slug_preserves_stats() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+        let old = Slug("old_slug".into());
+        let new = Slug("new_slug".into());
+        service.handle_create_short_link(url.clone(), Some(old.clone())).unwrap();
+        service.handle_redirect(old.clone()).unwrap();
+
+        let renamed = service.handle_rename_slug(old.clone(), new.clone()).unwrap();
+
+        assert_eq!(renamed, ShortLink { slug: new.clone(), url: url.clone() });
+        let stats = service.get_stats(new.clone()).unwrap();
+        assert_eq!(stats.link.url, url);
+        assert_eq!(stats.redirects, 1);
+        assert_eq!(service.get_stats(old.clone()), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.handle_redirect(old), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.handle_redirect(new).unwrap().url, url);
+    }
+
+    #[test]
+    fn test_rename_missing_slug_fails() {
+        let mut service = UrlShortenerService::new();
+
+        let result = service.handle_rename_slug(Slug("missing".into()), Slug("new_slug".into()));
+        assert_eq!(result, Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_rename_onto_occupied_slug_fails() {
+        let mut service = UrlShortenerService::new();
+        let first = Slug("first".into());
+        let second = Slug("second".into());
+        service.handle_create_short_link(Url("https://example.com/1".into()), Some(first.clone())).unwrap();
+        service.handle_create_short_link(Url("https://example.com/2".into()), Some(second.clone())).unwrap();
+
+        let result = service.handle_rename_slug(first.clone(), second.clone());
+
+        assert_eq!(result, Err(ShortenerError::SlugAlreadyInUse));
+        assert_eq!(service.get_stats(first).unwrap().link.url, Url("https://example.com/1".into()));
+        assert_eq!(service.get_stats(second).unwrap().link.url, Url("https://example.com/2".into()));
+    }
+
+    #[test]
+    fn test_redirect_limit_stops_redirects_after_cap() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com/promo".into());
+        let slug = Slug("promo".into());
+        service.handle_create_short_link_with_limit(url.clone(), Some(slug.clone()), 2).unwrap();
+
+        assert_eq!(service.handle_redirect(slug.clone()).unwrap().url, url);
+        assert_eq!(service.handle_redirect(slug.clone()).unwrap().url, url);
+        assert_eq!(service.handle_redirect(slug.clone()), Err(ShortenerError::RedirectLimitReached));
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 2);
+    }
+
+    #[test]
+    fn test_links_are_unlimited_by_default() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+
+        for _ in 0..10 {
+            service.handle_redirect(slug.clone()).unwrap();
+        }
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 10);
+    }
+
+    #[test]
+    fn test_events_since_returns_only_new_events() {
+        let mut service = UrlShortenerService::new();
+        let clock = MockClock::new();
+        service.set_clock(clock.clone());
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+
+        assert_eq!(service.events_since(0).len(), 2);
+        let cursor = service.event_count();
+        assert_eq!(cursor, 2);
+        assert!(service.events_since(cursor).is_empty());
+
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_change_short_link(slug.clone(), Url("https://example.com/new".into())).unwrap();
+
+        assert_eq!(
+            service.events_since(cursor),
+            &[
+                Event::LinkRedirected { slug: slug.clone(), referrer: None, country: None, at: clock.now() },
+                Event::LinkUrlChanged { slug, url: Url("https://example.com/new".into()) },
+            ],
+        );
+        assert!(service.events_since(100).is_empty());
+    }
+
+    #[test]
+    fn test_redirect_with_query_appends_to_url_without_query() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com/page".into()), Some(slug.clone())).unwrap();
+
+        let link = service.handle_redirect_with_query(slug.clone(), "utm=x").unwrap();
+
+        assert_eq!(link.url, Url("https://example.com/page?utm=x".into()));
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 1);
+    }
+
+    #[test]
+    fn test_redirect_with_query_extends_existing_query() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com/page?a=1#top".into()), Some(slug.clone())).unwrap();
+
+        let link = service.handle_redirect_with_query(slug, "?utm=x").unwrap();
+
+        assert_eq!(link.url, Url("https://example.com/page?a=1&utm=x#top".into()));
+    }
+
+    #[test]
+    fn test_redirect_with_query_incoming_keys_win() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com/page?utm=old&a=1".into()), Some(slug.clone())).unwrap();
+
+        let link = service.handle_redirect_with_query(slug.clone(), "utm=new&b=2").unwrap();
+        assert_eq!(link.url, Url("https://example.com/page?utm=new&a=1&b=2".into()));
+
+        // The stored destination itself is left untouched.
+        let link = service.handle_redirect_with_query(slug, "").unwrap();
+        assert_eq!(link.url, Url("https://example.com/page?utm=old&a=1".into()));
+    }
+
+    #[test]
+    fn test_summary_reflects_links_events_and_redirects() {
+        let mut service = UrlShortenerService::new();
+        assert_eq!(
+            service.summary(),
+            ServiceSummary { link_count: 0, total_events: 0, total_redirects: 0 },
+        );
+
+        let first = service.handle_create_short_link(Url("https://example.com/1".into()), None).unwrap();
+        let second = service.handle_create_short_link(Url("https://example.com/2".into()), None).unwrap();
+        service.handle_redirect(first.slug.clone()).unwrap();
+        service.handle_redirect(first.slug).unwrap();
+        service.handle_redirect(second.slug).unwrap();
+
+        let summary = service.summary();
+        assert_eq!(summary.link_count, 2);
+        assert_eq!(summary.total_events, 5);
+        assert_eq!(summary.total_redirects, 3);
+    }
+
+    #[test]
+    fn test_error_display_messages() {
+        assert_eq!(ShortenerError::InvalidUrl.to_string(), "invalid URL");
+        assert_eq!(ShortenerError::SlugAlreadyInUse.to_string(), "slug is already in use");
+        assert_eq!(ShortenerError::SlugNotFound.to_string(), "slug not found");
+        assert_eq!(ShortenerError::UrlTooLong.to_string(), "URL is too long");
+        assert_eq!(ShortenerError::RedirectLimitReached.to_string(), "redirect limit is reached");
+        assert_eq!(ShortenerError::RateLimited.to_string(), "too many redirects, try again later");
+        assert_eq!(ShortenerError::PreconditionFailed.to_string(), "precondition failed");
+        assert_eq!(ShortenerError::InvalidSlug.to_string(), "invalid slug");
+        assert_eq!(ShortenerError::InvalidState.to_string(), "invalid saved state");
+        assert_eq!(ShortenerError::LinkExpired.to_string(), "link is expired");
+        assert_eq!(ShortenerError::LinkLimitReached.to_string(), "link limit is reached");
+        assert_eq!(ShortenerError::InvalidConfig.to_string(), "invalid configuration");
+        assert_eq!(ShortenerError::CounterOverflow.to_string(), "click count overflow");
+        assert_eq!(ShortenerError::ChecksumMismatch.to_string(), "events checksum mismatch");
+        assert_eq!(ShortenerError::DomainBlocked.to_string(), "domain is blocked");
+        assert_eq!(ShortenerError::RedirectLoop.to_string(), "redirect loop detected");
+        assert_eq!(ShortenerError::LinkImmutable.to_string(), "link is immutable");
+        assert_eq!(ShortenerError::RedirectDenied.to_string(), "redirect denied");
+        assert_eq!(
+            ShortenerError::SlugSpaceExhausted.to_string(),
+            "no free slug could be generated",
+        );
+        assert_eq!(
+            ShortenerError::UrlAlreadyShortened(Slug("abc".into())).to_string(),
+            "URL is already shortened as `abc`",
+        );
+    }
+
+    #[test]
+    fn test_error_can_be_boxed_as_dyn_error() {
+        fn redirect(service: &mut UrlShortenerService) -> Result<ShortLink, Box<dyn Error>> {
+            Ok(service.handle_redirect(Slug("missing".into()))?)
+        }
+
+        let err = redirect(&mut UrlShortenerService::new()).unwrap_err();
+        assert_eq!(err.to_string(), "slug not found");
+        assert_eq!(err.downcast_ref::<ShortenerError>(), Some(&ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_reserve_then_commit_creates_link() {
+        let mut service = UrlShortenerService::new();
+        let clock = MockClock::new();
+        service.set_clock(clock.clone());
+        let url = Url("https://example.com".into());
+        let slug = Slug("my_slug".into());
+
+        service.reserve(slug.clone(), Duration::from_secs(60)).unwrap();
+        assert_eq!(service.reserve(slug.clone(), Duration::from_secs(60)), Err(ShortenerError::SlugAlreadyInUse));
+        assert_eq!(
+            service.handle_create_short_link(url.clone(), Some(slug.clone())),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+
+        clock.advance(Duration::from_secs(30));
+        let link = service.commit_reserved(slug.clone(), url.clone()).unwrap();
+
+        assert_eq!(link, ShortLink { slug: slug.clone(), url: url.clone() });
+        assert_eq!(service.get_stats(slug.clone()).unwrap().link.url, url);
+        assert_eq!(service.reserve(slug, Duration::from_secs(60)), Err(ShortenerError::SlugAlreadyInUse));
+    }
+
+    #[test]
+    fn test_expired_reservation_frees_slug() {
+        let mut service = UrlShortenerService::new();
+        let clock = MockClock::new();
+        service.set_clock(clock.clone());
+        let slug = Slug("my_slug".into());
+
+        service.reserve(slug.clone(), Duration::from_secs(60)).unwrap();
+        clock.advance(Duration::from_secs(60));
+
+        assert_eq!(service.reserve(slug, Duration::from_secs(60)), Ok(()));
+    }
+
+    #[test]
+    fn test_commit_of_expired_reservation_fails() {
+        let mut service = UrlShortenerService::new();
+        let clock = MockClock::new();
+        service.set_clock(clock.clone());
+        let slug = Slug("my_slug".into());
+
+        service.reserve(slug.clone(), Duration::from_secs(60)).unwrap();
+        clock.advance(Duration::from_secs(61));
+
+        let result = service.commit_reserved(slug.clone(), Url("https://example.com".into()));
+        assert_eq!(result, Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.get_stats(slug), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_links_created_between_counts_creations_in_window() {
+        let mut service = UrlShortenerService::new();
+        let clock = MockClock::new();
+        service.set_clock(clock.clone());
+        let start = clock.now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        for secs in [10, 20, 30] {
+            clock.set(at(secs));
+            service.handle_create_short_link(Url(format!("https://example.com/{secs}")), Some(Slug(format!("s{secs}")))).unwrap();
+        }
+        clock.set(at(25));
+        service.handle_change_short_link(Slug("s10".into()), Url("https://example.com/new".into())).unwrap();
+
+        assert_eq!(service.links_created_between(at(0), at(100)), 3);
+        assert_eq!(service.links_created_between(at(10), at(30)), 2);
+        assert_eq!(service.links_created_between(at(11), at(30)), 1);
+        assert_eq!(service.links_created_between(at(21), at(30)), 0);
+        assert_eq!(service.links_created_between(at(30), at(10)), 0);
+    }
+
+    #[test]
+    fn test_create_detailed_reports_new_link() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+
+        let first = service.handle_create_short_link_detailed(url.clone(), None).unwrap();
+        let second = service.handle_create_short_link_detailed(url.clone(), None).unwrap();
+
+        assert!(first.created);
+        assert!(second.created);
+        assert_ne!(first.link.slug, second.link.slug);
+        assert_eq!(service.event_count(), 2);
+    }
+
+    #[test]
+    fn test_create_detailed_reports_deduped_existing_link() {
+        let mut service = UrlShortenerService::with_config(Config { dedupe_urls: true, ..Config::default() });
+        let url = Url("https://example.com".into());
+
+        let first = service.handle_create_short_link_detailed(url.clone(), None).unwrap();
+        let second = service.handle_create_short_link_detailed(url.clone(), None).unwrap();
+
+        assert!(first.created);
+        assert!(!second.created);
+        assert_eq!(second.link, first.link);
+        assert_eq!(service.event_count(), 1);
+
+        // Changing the destination away frees the URL for a new link.
+        service.handle_change_short_link(first.link.slug.clone(), Url("https://example.com/new".into())).unwrap();
+        let third = service.handle_create_short_link_detailed(url, None).unwrap();
+        assert!(third.created);
+        assert_ne!(third.link.slug, first.link.slug);
+    }
+
+    #[test]
+    fn test_iter_events_yields_sequence_numbers_in_order() {
+        let mut service = UrlShortenerService::new();
+        let clock = MockClock::new();
+        service.set_clock(clock.clone());
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+
+        let events: Vec<_> = service.iter_events().collect();
+
+        assert_eq!(events.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert!(matches!(events[0].1, Event::LinkCreated { slug: created, .. } if *created == slug));
+        let at = clock.now();
+        assert_eq!(events[1].1, &Event::LinkRedirected { slug: slug.clone(), referrer: None, country: None, at });
+        assert_eq!(events[2].1, &Event::LinkRedirected { slug, referrer: None, country: None, at });
+    }
+
+    #[test]
+    fn test_default_slug_policy_rejects_disallowed_characters() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+
+        assert!(service.handle_create_short_link(url.clone(), Some(Slug("My-slug_1".into()))).is_ok());
+        assert_eq!(
+            service.handle_create_short_link(url.clone(), Some(Slug("my.slug".into()))),
+            Err(ShortenerError::InvalidSlug),
+        );
+        assert_eq!(
+            service.handle_create_short_link(url, Some(Slug("".into()))),
+            Err(ShortenerError::InvalidSlug),
+        );
+    }
+
+    #[test]
+    fn test_custom_slug_policy_forbidding_uppercase() {
+        struct LowercasePolicy;
+
+        impl SlugPolicy for LowercasePolicy {
+            fn is_valid(&self, slug: &str) -> bool {
+                !slug.is_empty() && !slug.chars().any(|c| c.is_uppercase())
+            }
+        }
+
+        let mut service = UrlShortenerService::new();
+        service.set_slug_policy(LowercasePolicy);
+        let url = Url("https://example.com".into());
+
+        assert_eq!(
+            service.handle_create_short_link(url.clone(), Some(Slug("MySlug".into()))),
+            Err(ShortenerError::InvalidSlug),
+        );
+        assert!(service.handle_create_short_link(url, Some(Slug("my.slug".into()))).is_ok());
+    }
+
+    #[test]
+    fn test_requires_encoding() {
+        assert!(requires_encoding("my slug"));
+        assert!(requires_encoding("my#slug"));
+        assert!(requires_encoding("my/slug"));
+        assert!(requires_encoding("слаг"));
+        assert!(!requires_encoding("my-slug_1.v2~"));
+    }
+
+    #[test]
+    fn test_slugs_requiring_encoding_are_rejected_regardless_of_policy() {
+        struct AllowAllPolicy;
+
+        impl SlugPolicy for AllowAllPolicy {
+            fn is_valid(&self, _: &str) -> bool {
+                true
+            }
+        }
+
+        let mut service = UrlShortenerService::new();
+        service.set_slug_policy(AllowAllPolicy);
+        let url = Url("https://example.com".into());
+
+        assert_eq!(
+            service.handle_create_short_link(url.clone(), Some(Slug("my slug".into()))),
+            Err(ShortenerError::InvalidSlug),
+        );
+        assert_eq!(
+            service.handle_create_short_link(url.clone(), Some(Slug("my#slug".into()))),
+            Err(ShortenerError::InvalidSlug),
+        );
+        assert!(service.handle_create_short_link(url, Some(Slug("my_slug".into()))).is_ok());
+    }
+
+    #[test]
+    fn test_referrers_histogram() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+
+        service.handle_redirect_with_referrer(slug.clone(), Some("twitter.com".into())).unwrap();
+        service.handle_redirect_with_referrer(slug.clone(), Some("news.ycombinator.com".into())).unwrap();
+        service.handle_redirect_with_referrer(slug.clone(), Some("twitter.com".into())).unwrap();
+        service.handle_redirect_with_referrer(slug.clone(), None).unwrap();
+
+        assert_eq!(
+            service.referrers(slug.clone()),
+            Ok(HashMap::from([
+                ("twitter.com".to_string(), 2),
+                ("news.ycombinator.com".to_string(), 1),
+                (DIRECT_REFERRER.to_string(), 1),
+            ])),
+        );
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 4);
+        assert_eq!(service.referrers(Slug("missing".into())), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_save_and_load_state_round_trip() {
+        let mut service = UrlShortenerService::with_config(Config { max_url_len: 64, ..Config::default() });
+        let slug = Slug("my_slug".into());
+        let limited = Slug("limited".into());
+        let meta = HashMap::from([("campaign".to_string(), "spring".to_string())]);
+        service.handle_create_short_link_with_meta(Url("https://example.com".into()), Some(slug.clone()), meta.clone()).unwrap();
+        service.handle_create_short_link_with_limit(Url("https://example.com/promo".into()), Some(limited.clone()), 1).unwrap();
+        service.handle_redirect_with_referrer(slug.clone(), Some("twitter.com".into())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_redirect(limited.clone()).unwrap();
+
+        let mut loaded = UrlShortenerService::load_state(&service.save_state()).unwrap();
+
+        assert_eq!(loaded.event_count(), 0);
+        assert_eq!(loaded.get_stats(slug.clone()), service.get_stats(slug.clone()));
+        assert_eq!(loaded.get_stats(limited.clone()), service.get_stats(limited.clone()));
+        assert_eq!(loaded.get_meta(slug.clone()), Ok(meta));
+        assert_eq!(loaded.referrers(slug.clone()), service.referrers(slug.clone()));
+        assert_eq!(loaded.config.max_url_len, 64);
+        assert_eq!(loaded.handle_redirect(limited), Err(ShortenerError::RedirectLimitReached));
+        assert_eq!(loaded.handle_redirect(slug.clone()).unwrap().url, Url("https://example.com".into()));
+        assert_eq!(loaded.get_stats(slug).unwrap().redirects, 3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_load_state_rejects_invalid_json() {
+        assert!(matches!(UrlShortenerService::load_state("{"), Err(ShortenerError::InvalidState)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_events_jsonl_round_trip() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let meta = HashMap::from([("campaign".to_string(), "spring".to_string())]);
+        service.handle_create_short_link_with_meta(Url("https://example.com".into()), Some(slug.clone()), meta).unwrap();
+        service.handle_change_short_link(slug.clone(), Url("https://example.org".into())).unwrap();
+        service.handle_redirect_with_referrer(slug.clone(), Some("twitter.com".into())).unwrap();
+
+        let jsonl = service.export_events_jsonl();
+        assert_eq!(jsonl.lines().count(), 3);
+
+        let imported = UrlShortenerService::import_events_jsonl(&format!("\n{jsonl}\n")).unwrap();
+        assert_eq!(imported.events, service.events);
+        assert_eq!(imported.get_stats(slug.clone()), service.get_stats(slug.clone()));
+        assert_eq!(imported.referrers(slug.clone()), service.referrers(slug));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_import_events_jsonl_reports_malformed_line() {
+        let mut service = UrlShortenerService::new();
+        service.handle_create_short_link(Url("https://example.com".into()), None).unwrap();
+        let jsonl = format!("{}\n{{\"Unknown\":{{}}}}\n", service.export_events_jsonl());
+
+        let err = UrlShortenerService::import_events_jsonl(&jsonl).map(|_| ()).unwrap_err();
+        assert_eq!(err, ImportError { line: 3 });
+        assert_eq!(err.to_string(), "malformed event at line 3");
+    }
+
+    #[test]
+    fn test_merge_imports_links_of_other_service() {
+        let mut service = UrlShortenerService::new();
+        let mut other = UrlShortenerService::new();
+        let mine = Slug("mine".into());
+        let theirs = Slug("theirs".into());
+        let meta = HashMap::from([("campaign".to_string(), "spring".to_string())]);
+        service.handle_create_short_link(Url("https://example.com/mine".into()), Some(mine.clone())).unwrap();
+        other.handle_create_short_link_with_meta(Url("https://example.com/theirs".into()), Some(theirs.clone()), meta.clone()).unwrap();
+        other.handle_redirect(theirs.clone()).unwrap();
+
+        service.merge(other).unwrap();
+
+        assert_eq!(service.get_stats(mine).unwrap().redirects, 0);
+        let stats = service.get_stats(theirs.clone()).unwrap();
+        assert_eq!(stats.link.url, Url("https://example.com/theirs".into()));
+        assert_eq!(stats.redirects, 1);
+        assert_eq!(service.get_meta(theirs), Ok(meta));
+    }
+
+    #[test]
+    fn test_merge_with_conflicting_slug_fails_without_changes() {
+        let mut service = UrlShortenerService::new();
+        let mut other = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com/a".into()), Some(slug.clone())).unwrap();
+        other.handle_create_short_link(Url("https://example.com/b".into()), Some(slug.clone())).unwrap();
+        other.handle_create_short_link(Url("https://example.com/c".into()), Some(Slug("other".into()))).unwrap();
+
+        assert_eq!(service.merge(other), Err(DetailedError::SlugAlreadyInUse(slug.clone())));
+        assert_eq!(service.get_stats(slug).unwrap().link.url, Url("https://example.com/a".into()));
+        assert_eq!(service.get_stats(Slug("other".into())), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.event_count(), 1);
+    }
+
+    #[test]
+    fn test_merge_sums_click_counts_of_shared_slug() {
+        let mut service = UrlShortenerService::new();
+        let mut other = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+        other.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        for _ in 0..3 {
+            other.handle_redirect(slug.clone()).unwrap();
+        }
+
+        service.merge(other).unwrap();
+
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 4);
+        assert_eq!(service.click_counts.get(&slug), Some(&4));
+    }
+
+    #[test]
+    fn test_validate_create_accepts_valid_request_without_changes() {
+        let mut service = UrlShortenerService::new();
+        service.handle_create_short_link(Url("https://example.com".into()), Some(Slug("taken".into()))).unwrap();
+
+        let url = Url("https://example.com/new".into());
+        assert_eq!(service.validate_create(&url, Some(&Slug("free".into()))), Ok(()));
+        assert_eq!(service.validate_create(&url, None), Ok(()));
+        assert_eq!(service.event_count(), 1);
+        assert_eq!(service.get_stats(Slug("free".into())), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_validate_create_reports_duplicate_slug() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("taken".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+
+        let url = Url("https://example.com/new".into());
+        assert_eq!(service.validate_create(&url, Some(&slug)), Err(ShortenerError::SlugAlreadyInUse));
+        assert_eq!(service.validate_create(&url, Some(&Slug("a b".into()))), Err(ShortenerError::InvalidSlug));
+        assert_eq!(service.get_stats(slug).unwrap().link.url, Url("https://example.com".into()));
+        assert_eq!(service.event_count(), 1);
+    }
+
+    #[test]
+    fn test_validate_create_reports_invalid_url() {
+        let mut service = UrlShortenerService::new();
+
+        for url in ["not a url", "example.com", "https://", "https:///path", "https://exa mple.com"] {
+            let url = Url(url.into());
+            assert_eq!(service.validate_create(&url, None), Err(ShortenerError::InvalidUrl));
+            assert_eq!(service.handle_create_short_link(url, None), Err(ShortenerError::InvalidUrl));
+        }
+        assert_eq!(service.event_count(), 0);
+    }
+
+    #[test]
+    fn test_https_scheme_is_allowed_by_default() {
+        let mut service = UrlShortenerService::new();
+
+        assert!(service.handle_create_short_link(Url("https://example.com".into()), None).is_ok());
+        assert!(service.handle_create_short_link(Url("HTTP://example.com".into()), None).is_ok());
+    }
+
+    #[test]
+    fn test_ftp_scheme_is_rejected_by_default() {
+        let mut service = UrlShortenerService::new();
+
+        let result = service.handle_create_short_link(Url("ftp://example.com/file".into()), None);
+        assert_eq!(result, Err(ShortenerError::InvalidUrl));
+    }
+
+    #[test]
+    fn test_ftp_scheme_is_accepted_once_allowed() {
+        let mut config = Config::default();
+        config.allowed_schemes.push("FTP".into());
+        let mut service = UrlShortenerService::with_config(config);
+
+        assert!(service.handle_create_short_link(Url("ftp://example.com/file".into()), None).is_ok());
+    }
+
+    #[test]
+    fn test_events_of_type_filters_by_kind() {
+        let mut service = UrlShortenerService::new();
+        let first = service.handle_create_short_link(Url("https://example.com/1".into()), None).unwrap();
+        let second = service.handle_create_short_link(Url("https://example.com/2".into()), None).unwrap();
+        service.handle_redirect(first.slug.clone()).unwrap();
+        service.handle_redirect(second.slug.clone()).unwrap();
+        service.handle_redirect(first.slug.clone()).unwrap();
+        service.handle_change_short_link(first.slug, Url("https://example.com/3".into())).unwrap();
+
+        assert_eq!(service.events_of_type(EventKind::LinkCreated).count(), 2);
+        assert_eq!(service.events_of_type(EventKind::LinkRedirected).count(), 3);
+        assert_eq!(service.events_of_type(EventKind::LinkUrlChanged).count(), 1);
+        assert_eq!(service.events_of_type(EventKind::SlugRenamed).count(), 0);
+        assert!(service
+            .events_of_type(EventKind::LinkRedirected)
+            .all(|event| matches!(event, Event::LinkRedirected { .. })));
+    }
+
+    #[test]
+    fn test_rate_limit_rejects_redirects_beyond_window_budget() {
+        let mut service = UrlShortenerService::with_config(Config {
+            rate_limit: Some((2, Duration::from_secs(10))),
+            ..Config::default()
+        });
+        let clock = MockClock::new();
+        service.set_clock(clock.clone());
+        let slug = Slug("my_slug".into());
+        let other = Slug("other".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_create_short_link(Url("https://example.com/other".into()), Some(other.clone())).unwrap();
+
+        service.handle_redirect(slug.clone()).unwrap();
+        clock.advance(Duration::from_secs(5));
+        service.handle_redirect(slug.clone()).unwrap();
+        assert_eq!(service.handle_redirect(slug.clone()), Err(ShortenerError::RateLimited));
+        // The budget is tracked per slug.
+        assert!(service.handle_redirect(other).is_ok());
+
+        // The first redirect leaves the window.
+        clock.advance(Duration::from_secs(5));
+        service.handle_redirect(slug.clone()).unwrap();
+        assert_eq!(service.handle_redirect(slug.clone()), Err(ShortenerError::RateLimited));
+
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 3);
+    }
+
+    #[test]
+    fn test_redirects_are_not_rate_limited_by_default() {
+        let mut service = UrlShortenerService::new();
+        service.set_clock(MockClock::new());
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+
+        for _ in 0..100 {
+            service.handle_redirect(slug.clone()).unwrap();
+        }
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 100);
+    }
+
+    #[test]
+    fn test_deleted_link_stops_resolving() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+
+        service.handle_delete_short_link(slug.clone()).unwrap();
+
+        assert_eq!(service.handle_redirect(slug.clone()), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.get_stats(slug.clone()), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.handle_delete_short_link(slug.clone()), Err(ShortenerError::SlugNotFound));
+        assert!(service.handle_create_short_link(Url("https://example.com/new".into()), Some(slug)).is_ok());
+    }
+
+    #[test]
+    fn test_restore_deleted_link() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let url = Url("https://example.com/new".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_change_short_link(slug.clone(), url.clone()).unwrap();
+        service.handle_delete_short_link(slug.clone()).unwrap();
+
+        let restored = service.restore(slug.clone()).unwrap();
+
+        assert_eq!(restored, ShortLink { slug: slug.clone(), url: url.clone() });
+        assert_eq!(service.handle_redirect(slug.clone()).unwrap().url, url);
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 1);
+        assert_eq!(service.events.last().map(Event::kind), Some(EventKind::LinkRedirected));
+        assert_eq!(service.events_of_type(EventKind::LinkRestored).count(), 1);
+        assert_eq!(service.restore(slug), Err(ShortenerError::SlugAlreadyInUse));
+    }
+
+    #[test]
+    fn test_restore_never_seen_slug_fails() {
+        let mut service = UrlShortenerService::new();
+
+        assert_eq!(service.restore(Slug("missing".into())), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.event_count(), 0);
+    }
+
+    #[test]
+    fn test_event_listener_is_notified_about_recorded_events() {
+        let mut service = UrlShortenerService::new();
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&captured);
+        service.set_event_listener(move |event| sink.lock().unwrap().push(format!("{:?}", event.kind())));
+        let slug = Slug("my_slug".into());
+
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_redirect(Slug("missing".into())).unwrap_err();
+
+        assert_eq!(*captured.lock().unwrap(), vec!["LinkCreated", "LinkRedirected"]);
+    }
+
+    #[test]
+    fn test_state_fingerprint_ignores_build_order() {
+        let mut first = UrlShortenerService::new();
+        let mut second = UrlShortenerService::new();
+        let a = Slug("a".into());
+        let b = Slug("b".into());
+
+        first.handle_create_short_link(Url("https://example.com/a".into()), Some(a.clone())).unwrap();
+        first.handle_create_short_link(Url("https://example.com/b".into()), Some(b.clone())).unwrap();
+        first.handle_redirect(a.clone()).unwrap();
+
+        second.handle_create_short_link(Url("https://example.com/old".into()), Some(b.clone())).unwrap();
+        second.handle_create_short_link(Url("https://example.com/a".into()), Some(a.clone())).unwrap();
+        second.handle_redirect(a.clone()).unwrap();
+        second.handle_change_short_link(b, Url("https://example.com/b".into())).unwrap();
+
+        assert_eq!(first.state_fingerprint(), second.state_fingerprint());
+
+        second.handle_redirect(a).unwrap();
+        assert_ne!(first.state_fingerprint(), second.state_fingerprint());
+    }
+
+    #[test]
+    fn test_state_fingerprint_differs_for_different_state() {
+        let mut first = UrlShortenerService::new();
+        let mut second = UrlShortenerService::new();
+        first.handle_create_short_link(Url("https://example.com/ab".into()), Some(Slug("a".into()))).unwrap();
+        second.handle_create_short_link(Url("https://example.com/b".into()), Some(Slug("a".into()))).unwrap();
+
+        assert_ne!(first.state_fingerprint(), second.state_fingerprint());
+        assert_ne!(first.state_fingerprint(), UrlShortenerService::new().state_fingerprint());
+    }
+
+    #[test]
+    fn test_alias_shares_url_and_click_count() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+        let canonical = Slug("canonical".into());
+        let alias = Slug("vanity".into());
+        service.handle_create_short_link(url.clone(), Some(canonical.clone())).unwrap();
+
+        let link = service.handle_create_alias(canonical.clone(), alias.clone()).unwrap();
+        assert_eq!(link, ShortLink { slug: alias.clone(), url: url.clone() });
+
+        assert_eq!(service.handle_redirect(alias.clone()).unwrap(), ShortLink { slug: alias.clone(), url: url.clone() });
+        service.handle_redirect(canonical.clone()).unwrap();
+
+        let stats = service.get_stats(canonical.clone()).unwrap();
+        assert_eq!(stats.redirects, 2);
+        assert_eq!(service.get_stats(alias.clone()), Ok(stats));
+        assert_eq!(
+            service.handle_create_short_link(url, Some(alias.clone())),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+
+        service.handle_delete_short_link(canonical).unwrap();
+        assert_eq!(service.handle_redirect(alias), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_alias_of_missing_slug_fails() {
+        let mut service = UrlShortenerService::new();
+
+        let result = service.handle_create_alias(Slug("missing".into()), Slug("vanity".into()));
+        assert_eq!(result, Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_alias_onto_taken_slug_fails() {
+        let mut service = UrlShortenerService::new();
+        let first = Slug("first".into());
+        let second = Slug("second".into());
+        service.handle_create_short_link(Url("https://example.com/1".into()), Some(first.clone())).unwrap();
+        service.handle_create_short_link(Url("https://example.com/2".into()), Some(second.clone())).unwrap();
+
+        assert_eq!(service.handle_create_alias(first, second.clone()), Err(ShortenerError::SlugAlreadyInUse));
+        assert_eq!(service.get_stats(second).unwrap().link.url, Url("https://example.com/2".into()));
+    }
+
+    #[test]
+    fn test_estimated_memory_bytes_grows_with_data() {
+        let mut service = UrlShortenerService::new();
+        assert_eq!(service.estimated_memory_bytes(), 0);
+
+        let first = service.handle_create_short_link(Url("https://example.com/1".into()), None).unwrap();
+        let one_link = service.estimated_memory_bytes();
+        assert!(one_link > 0);
+
+        service.handle_create_short_link(Url("https://example.com/2".into()), None).unwrap();
+        let two_links = service.estimated_memory_bytes();
+        assert!(two_links > one_link);
+
+        service.handle_redirect(first.slug).unwrap();
+        assert!(service.estimated_memory_bytes() > two_links);
+    }
+
+    #[test]
+    fn test_replay_into_custom_visitor() {
+        #[derive(Default)]
+        struct HostPopularity {
+            hosts: HashMap<Slug, String>,
+            redirects: HashMap<String, u64>,
+        }
+
+        impl EventVisitor for HostPopularity {
+            fn on_created(&mut self, slug: &Slug, url: &Url) {
+                self.hosts.insert(slug.clone(), host_of(&url.0).unwrap().to_owned());
+            }
+
+            fn on_redirected(&mut self, slug: &Slug) {
+                *self.redirects.entry(self.hosts[slug].clone()).or_default() += 1;
+            }
+        }
+
+        let mut service = UrlShortenerService::new();
+        let a = service.handle_create_short_link(Url("https://a.com/1".into()), None).unwrap();
+        let b = service.handle_create_short_link(Url("https://user@b.com:8080/1".into()), None).unwrap();
+        let c = service.handle_create_short_link(Url("https://a.com/2".into()), None).unwrap();
+        service.handle_redirect(a.slug).unwrap();
+        service.handle_redirect(b.slug).unwrap();
+        service.handle_redirect(c.slug.clone()).unwrap();
+        service.handle_redirect(c.slug).unwrap();
+
+        let mut visitor = HostPopularity::default();
+        service.replay_into(&mut visitor);
+
+        assert_eq!(
+            visitor.redirects,
+            HashMap::from([("a.com".to_string(), 3), ("b.com".to_string(), 1)]),
+        );
+    }
+
+    #[test]
+    fn test_change_if_matching_expected_url_succeeds() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let url = Url("https://example.com".into());
+        let new_url = Url("https://example.com/new".into());
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+
+        let link = service.handle_change_if(slug.clone(), url, new_url.clone()).unwrap();
+
+        assert_eq!(link, ShortLink { slug: slug.clone(), url: new_url.clone() });
+        assert_eq!(service.get_stats(slug).unwrap().link.url, new_url);
+    }
+
+    #[test]
+    fn test_change_if_mismatched_expected_url_fails() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+
+        let result = service.handle_change_if(
+            slug.clone(),
+            Url("https://example.com/stale".into()),
+            Url("https://example.com/new".into()),
+        );
+
+        assert_eq!(result, Err(ShortenerError::PreconditionFailed));
+        assert_eq!(service.get_stats(slug).unwrap().link.url, url);
+        assert_eq!(service.event_count(), 1);
+    }
+
+    #[test]
+    fn test_change_if_missing_slug_fails() {
+        let mut service = UrlShortenerService::new();
+
+        let result = service.handle_change_if(
+            Slug("missing".into()),
+            Url("https://example.com".into()),
+            Url("https://example.com/new".into()),
+        );
+        assert_eq!(result, Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_resolve_does_not_count_redirect() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+
+        assert_eq!(service.resolve(slug.clone()), Ok(ShortLink { slug: slug.clone(), url: url.clone() }));
+        assert_eq!(service.resolve(slug.clone()), Ok(ShortLink { slug: slug.clone(), url }));
+
+        let stats = service.get_link_with_stats(slug.clone()).unwrap();
+        assert_eq!(stats.redirects, 0);
+        assert_eq!(Ok(stats), service.get_stats(slug));
+    }
+
+    #[test]
+    fn test_resolve_missing_slug_fails() {
+        let service = UrlShortenerService::new();
+
+        assert_eq!(service.resolve(Slug("missing".into())), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.get_link_with_stats(Slug("missing".into())), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_seeded_rng_generates_same_slugs() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let mut first = UrlShortenerService::with_rng(StdRng::seed_from_u64(42));
+        let mut second = UrlShortenerService::with_rng(StdRng::seed_from_u64(42));
+
+        for _ in 0..3 {
+            let url = Url("https://example.com".into());
+            let a = first.handle_create_short_link(url.clone(), None).unwrap();
+            let b = second.handle_create_short_link(url, None).unwrap();
+            assert_eq!(a.slug, b.slug);
+            assert_eq!(a.slug.0.len(), 8);
+        }
+    }
+
+    #[test]
+    fn test_redirect_iter_includes_unvisited_links() {
+        let mut service = UrlShortenerService::new();
+        for slug in ["a", "b", "c"] {
+            service
+                .handle_create_short_link(Url("https://example.com".into()), Some(Slug(slug.into())))
+                .unwrap();
+        }
+        service.handle_redirect(Slug("b".into())).unwrap();
+
+        let mut counts: Vec<_> = service
+            .redirect_iter()
+            .map(|(slug, count)| (slug.0.clone(), count))
+            .collect();
+        counts.sort();
+
+        assert_eq!(counts, vec![("a".into(), 0), ("b".into(), 1), ("c".into(), 0)]);
+    }
+
+    #[test]
+    fn test_recent_links_newest_first() {
+        let mut service = UrlShortenerService::new();
+        for slug in ["a", "b", "c", "d"] {
+            service
+                .handle_create_short_link(Url("https://example.com".into()), Some(Slug(slug.into())))
+                .unwrap();
+        }
+
+        let slugs: Vec<_> = service.recent_links(2).into_iter().map(|link| link.slug).collect();
+        assert_eq!(slugs, vec![Slug("d".into()), Slug("c".into())]);
+
+        service.handle_delete_short_link(Slug("d".into())).unwrap();
+        let slugs: Vec<_> = service.recent_links(2).into_iter().map(|link| link.slug).collect();
+        assert_eq!(slugs, vec![Slug("c".into()), Slug("b".into())]);
+    }
+
+    #[test]
+    fn test_padded_slug_is_trimmed() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+
+        let link = service.handle_create_short_link(url.clone(), Some(Slug(" my_slug\n".into()))).unwrap();
+        assert_eq!(link.slug, Slug("my_slug".into()));
+
+        assert_eq!(service.handle_redirect(Slug("my_slug".into())).unwrap().url, url);
+        assert_eq!(service.handle_redirect(Slug("my_slug ".into())).unwrap().url, url);
+        assert_eq!(service.get_stats(Slug(" my_slug".into())).unwrap().redirects, 2);
+    }
+
+    #[test]
+    fn test_whitespace_slug_is_invalid() {
+        let mut service = UrlShortenerService::new();
+
+        assert_eq!(
+            service.handle_create_short_link(Url("https://example.com".into()), Some(Slug(" \t ".into()))),
+            Err(ShortenerError::InvalidSlug)
+        );
+    }
+
+    #[test]
+    fn test_hashed_slug_strategy_is_deterministic() {
+        let config = Config { slug_strategy: SlugStrategy::Hashed, ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config.clone());
+        let url = Url("https://example.com".into());
+
+        let first = service.handle_create_short_link_detailed(url.clone(), None).unwrap();
+        let second = service.handle_create_short_link_detailed(url.clone(), None).unwrap();
+        assert!(first.created);
+        assert!(!second.created);
+        assert_eq!(first.link, second.link);
+        assert_eq!(first.link.slug, hashed_slug(&url));
+
+        let mut other = UrlShortenerService::with_config(config);
+        assert_eq!(other.handle_create_short_link(url, None).unwrap(), first.link);
+    }
+
+    #[test]
+    fn test_hashed_slug_collision_gets_suffix() {
+        let config = Config { slug_strategy: SlugStrategy::Hashed, ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let url = Url("https://example.com".into());
+        let slug = hashed_slug(&url);
+
+        // Occupy the hashed slug with a different URL to emulate a collision.
+        service.handle_create_short_link(Url("https://other.com".into()), Some(slug.clone())).unwrap();
+
+        let link = service.handle_create_short_link(url.clone(), None).unwrap();
+        assert_eq!(link.slug, Slug(format!("{}-1", slug.0)));
+        assert_eq!(service.handle_create_short_link(url, None).unwrap(), link);
+    }
+
+    #[test]
+    fn test_event_counts_for_slug() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_change_short_link(slug.clone(), Url("https://example.org".into())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+
+        assert_eq!(service.event_counts_for(&slug), (1, 2));
+        assert_eq!(service.event_counts_for(&Slug("missing".into())), (0, 0));
+    }
+
+    #[test]
+    fn test_max_events_bounds_event_log() {
+        let config = Config { max_events: Some(10), ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let slug = Slug("my_slug".into());
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+        for _ in 0..100 {
+            service.handle_redirect(slug.clone()).unwrap();
+        }
+
+        assert_eq!(service.events.len(), 10);
+        assert_eq!(service.event_count(), 101);
+        assert_eq!(service.events_since(95).len(), 6);
+        assert_eq!(service.iter_events().next().map(|(i, _)| i), Some(91));
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 100);
+
+        for _ in 0..10 {
+            service.handle_redirect(slug.clone()).unwrap();
+        }
+        service.handle_delete_short_link(slug.clone()).unwrap();
+        assert_eq!(service.restore(slug).unwrap().url, url);
+    }
+
+    #[test]
+    fn test_record_redirects_in_batch() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+
+        service.handle_record_redirects(slug.clone(), 1000).unwrap();
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 1001);
+        assert_eq!(service.events_of_type(EventKind::RedirectsRecorded).count(), 1);
+
+        service.handle_record_redirects(slug.clone(), u64::MAX).unwrap();
+        assert_eq!(service.get_stats(slug).unwrap().redirects, u64::MAX);
+    }
+
+    #[test]
+    fn test_record_redirects_missing_slug_fails() {
+        let mut service = UrlShortenerService::new();
+
+        assert_eq!(
+            service.handle_record_redirects(Slug("missing".into()), 10),
+            Err(ShortenerError::SlugNotFound)
+        );
+    }
+
+    #[test]
+    fn test_find_by_url_contains() {
+        let mut service = UrlShortenerService::new();
+        for (slug, url) in [
+            ("c", "https://banned.com/a"),
+            ("a", "https://banned.com/b"),
+            ("b", "https://example.com"),
+            ("d", "https://BANNED.com"),
+        ] {
+            service.handle_create_short_link(Url(url.into()), Some(Slug(slug.into()))).unwrap();
+        }
+
+        let slugs: Vec<_> = service
+            .find_by_url_contains("banned.com")
+            .into_iter()
+            .map(|link| link.slug.0)
+            .collect();
+        assert_eq!(slugs, vec!["a", "c"]);
+        assert!(service.find_by_url_contains("missing.com").is_empty());
+    }
+
+    #[test]
+    fn test_verify_consistency() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_change_short_link(slug.clone(), Url("https://example.org".into())).unwrap();
+        service.handle_create_alias(slug.clone(), Slug("alias".into())).unwrap();
+        service.handle_redirect(Slug("alias".into())).unwrap();
+        service.handle_rename_slug(slug.clone(), Slug("renamed".into())).unwrap();
+        assert!(service.verify_consistency());
+
+        // Mutate the state directly, bypassing the event log.
+        service.click_counts.insert(Slug("renamed".into()), 10);
+        assert!(!service.verify_consistency());
+    }
+
+    #[test]
+    fn test_original_url_survives_change() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let url = Url("https://example.com".into());
+        let new_url = Url("https://example.org".into());
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+        service.handle_change_short_link(slug.clone(), new_url.clone()).unwrap();
+
+        assert_eq!(service.original_url(slug.clone()), Ok(url));
+        assert_eq!(service.get_stats(slug).unwrap().link.url, new_url);
+        assert_eq!(service.original_url(Slug("missing".into())), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_same_slug_in_different_namespaces() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let first_url = Url("https://first.com".into());
+        let second_url = Url("https://second.com".into());
+
+        let link = service.handle_create_short_link_ns("first", first_url.clone(), Some(slug.clone())).unwrap();
+        assert_eq!(link, ShortLink { slug: slug.clone(), url: first_url.clone() });
+        service.handle_create_short_link_ns("second", second_url.clone(), Some(slug.clone())).unwrap();
+        assert_eq!(
+            service.handle_create_short_link_ns("first", second_url.clone(), Some(slug.clone())),
+            Err(ShortenerError::SlugAlreadyInUse)
+        );
+
+        assert_eq!(service.handle_redirect_ns("first", slug.clone()).unwrap().url, first_url);
+        assert_eq!(service.handle_redirect_ns("second", slug.clone()).unwrap().url, second_url);
+        assert_eq!(service.handle_redirect_ns("second", slug.clone()).unwrap().url, second_url);
+
+        assert_eq!(service.get_stats_ns("first", slug.clone()).unwrap().redirects, 1);
+        let stats = service.get_stats_ns("second", slug.clone()).unwrap();
+        assert_eq!(stats, Stats { link: ShortLink { slug: slug.clone(), url: second_url }, redirects: 2 });
+
+        assert_eq!(service.handle_redirect(slug.clone()), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.handle_redirect_ns("third", slug), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_generated_slug_in_namespace() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+
+        let link = service.handle_create_short_link_ns("tenant", url.clone(), None).unwrap();
+        assert_eq!(link.slug.0.len(), 8);
+        assert_eq!(service.handle_redirect_ns("tenant", link.slug).unwrap().url, url);
+
+        assert_eq!(
+            service.handle_create_short_link_ns("bad/ns", url, None),
+            Err(ShortenerError::InvalidSlug)
+        );
+    }
+
+    #[test]
+    fn test_slug_and_url_parse_and_display() {
+        let slug: Slug = "abc".parse().unwrap();
+        assert_eq!(slug, Slug("abc".into()));
+        assert_eq!(slug.to_string(), "abc");
+
+        let url: Url = "https://example.com/path".parse().unwrap();
+        assert_eq!(url, Url("https://example.com/path".into()));
+        assert_eq!(url.to_string().parse::<Url>(), Ok(url));
+    }
+
+    #[test]
+    fn test_invalid_url_parse_fails() {
+        assert_eq!("example.com".parse::<Url>(), Err(ShortenerError::InvalidUrl));
+        assert_eq!("https://exa mple.com".parse::<Url>(), Err(ShortenerError::InvalidUrl));
+    }
+
+    #[test]
+    fn test_expired_link_keeps_stats() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_create_alias(slug.clone(), Slug("alias".into())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+
+        service.handle_expire_now(slug.clone()).unwrap();
+
+        assert_eq!(service.handle_redirect(slug.clone()), Err(ShortenerError::LinkExpired));
+        assert_eq!(service.handle_redirect(Slug("alias".into())), Err(ShortenerError::LinkExpired));
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 1);
+        assert_eq!(service.events_of_type(EventKind::LinkExpired).count(), 1);
+        assert!(service.verify_consistency());
+    }
+
+    #[test]
+    fn test_expire_missing_slug_fails() {
+        let mut service = UrlShortenerService::new();
+
+        assert_eq!(service.handle_expire_now(Slug("missing".into())), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_max_links_caps_creation() {
+        let config = Config { max_links: Some(2), ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let url = Url("https://example.com".into());
+
+        service.handle_create_short_link(url.clone(), Some(Slug("a".into()))).unwrap();
+        service.handle_create_short_link(url.clone(), Some(Slug("b".into()))).unwrap();
+        assert_eq!(
+            service.handle_create_short_link(url.clone(), Some(Slug("c".into()))),
+            Err(ShortenerError::LinkLimitReached)
+        );
+        assert_eq!(service.handle_create_short_link(url.clone(), None), Err(ShortenerError::LinkLimitReached));
+
+        service.handle_delete_short_link(Slug("a".into())).unwrap();
+        service.handle_create_short_link(url.clone(), Some(Slug("c".into()))).unwrap();
+        assert_eq!(service.restore(Slug("a".into())), Err(ShortenerError::LinkLimitReached));
+    }
+
+    #[test]
+    fn test_stats_with_min_clicks() {
+        let mut service = UrlShortenerService::new();
+        for (slug, redirects) in [("a", 1), ("b", 3), ("c", 0), ("d", 3)] {
+            let slug = Slug(slug.into());
+            service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+            service.handle_record_redirects(slug, redirects).unwrap();
+        }
+        let slugs = |min| -> Vec<_> {
+            service.stats_with_min_clicks(min).into_iter().map(|stats| stats.link.slug.0).collect()
+        };
+
+        assert_eq!(slugs(0), vec!["b", "d", "a", "c"]);
+        assert_eq!(slugs(2), vec!["b", "d"]);
+        assert!(slugs(4).is_empty());
+    }
+
+    #[test]
+    fn test_set_redirect_count_overrides_redirects() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        for _ in 0..3 {
+            service.handle_redirect(slug.clone()).unwrap();
+        }
+
+        service.handle_set_redirect_count(slug.clone(), 10).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 11);
+
+        let mut replayed = ReadModel::new();
+        for (_, event) in service.iter_events() {
+            replayed.apply(event);
+        }
+        assert_eq!(replayed.get_stats(slug.clone()).unwrap().redirects, 11);
+        assert!(service.verify_consistency());
+
+        assert_eq!(
+            service.handle_set_redirect_count(Slug("missing".into()), 1),
+            Err(ShortenerError::SlugNotFound)
+        );
+    }
+
+    #[test]
+    fn test_builder_configures_service() {
+        let mut service = UrlShortenerService::builder()
+            .max_url_len(64)
+            .dedupe_urls(true)
+            .allowed_schemes(["https"])
+            .rate_limit(10, Duration::from_secs(60))
+            .max_events(100)
+            .max_links(2)
+            .slug_length(4)
+            .slug_alphabet("ab")
+            .build()
+            .unwrap();
+        let url = Url("https://example.com".into());
+
+        let link = service.handle_create_short_link(url.clone(), None).unwrap();
+        assert_eq!(link.slug.0.len(), 4);
+        assert!(link.slug.0.chars().all(|c| c == 'a' || c == 'b'));
+        assert_eq!(service.handle_create_short_link(url, None), Ok(link));
+        assert_eq!(
+            service.handle_create_short_link(Url("http://example.com".into()), None),
+            Err(ShortenerError::InvalidUrl)
+        );
+        assert_eq!(service.config.max_url_len, 64);
+        assert_eq!(service.config.max_links, Some(2));
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_config() {
+        assert!(matches!(
+            UrlShortenerService::builder().slug_alphabet("").build(),
+            Err(ShortenerError::InvalidConfig)
+        ));
+        assert!(matches!(
+            UrlShortenerService::builder().slug_length(0).build(),
+            Err(ShortenerError::InvalidConfig)
+        ));
+        assert!(matches!(
+            UrlShortenerService::builder().allowed_schemes(Vec::<String>::new()).build(),
+            Err(ShortenerError::InvalidConfig)
+        ));
+    }
+
+    #[test]
+    fn test_country_breakdown() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect_with_country(slug.clone(), Some("DE".into())).unwrap();
+        service.handle_redirect_with_country(slug.clone(), Some("US".into())).unwrap();
+        service.handle_redirect_with_country(slug.clone(), Some("DE".into())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+
+        assert_eq!(
+            service.country_breakdown(slug),
+            Ok(HashMap::from([
+                ("DE".to_string(), 2),
+                ("US".to_string(), 1),
+                (UNKNOWN_COUNTRY.to_string(), 1),
+            ])),
+        );
+        assert_eq!(service.country_breakdown(Slug("missing".into())), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_create_idempotent_returns_same_link() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+
+        let first = service.handle_create_idempotent("key".into(), url.clone(), None).unwrap();
+        let second = service.handle_create_idempotent("key".into(), url.clone(), None).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(service.summary().link_count, 1);
+
+        let other = service.handle_create_idempotent("other".into(), url, None).unwrap();
+        assert_ne!(other.slug, first.slug);
+        assert!(service.verify_consistency());
+    }
+
+    #[test]
+    fn test_untracked_redirects_are_counted_without_events() {
+        let config = Config { track_redirect_events: false, ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_redirect_with_referrer(slug.clone(), Some("twitter.com".into())).unwrap();
+
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 2);
+        assert_eq!(service.referrers(slug).unwrap()["twitter.com"], 1);
+        assert_eq!(service.event_count(), 1);
+        assert!(service.iter_events().all(|(_, event)| event.kind() == EventKind::LinkCreated));
+    }
+
+    #[test]
+    fn test_public_events_round_trip() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_create_alias(slug.clone(), Slug("alias".into())).unwrap();
+        service.handle_redirect_with_country(Slug("alias".into()), Some("DE".into())).unwrap();
+        service.handle_rename_slug(slug.clone(), Slug("renamed".into())).unwrap();
+
+        let events = service.export_public_events();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[3], PublicEvent::SlugRenamed { old, .. } if *old == slug));
+
+        let imported = UrlShortenerService::from_public_events(events);
+        assert_eq!(imported.events, service.events);
+        assert_eq!(imported.state_fingerprint(), service.state_fingerprint());
+        assert_eq!(imported.get_stats(Slug("alias".into())), service.get_stats(Slug("alias".into())));
+    }
+
+    #[test]
+    fn test_slug_collisions_are_counted() {
+        let mut service = UrlShortenerService::new();
+        service.set_slug_generator(SequenceSlugGenerator::new(vec!["a", "a", "a", "b", "b", "c"]));
+        let url = Url("https://example.com".into());
+
+        service.handle_create_short_link(url.clone(), None).unwrap();
+        assert_eq!(service.slug_collision_count(), 0);
+        assert_eq!(service.handle_create_short_link(url.clone(), None).unwrap().slug, Slug("b".into()));
+        assert_eq!(service.slug_collision_count(), 2);
+        assert_eq!(service.handle_create_short_link(url, None).unwrap().slug, Slug("c".into()));
+        assert_eq!(service.slug_collision_count(), 3);
+    }
+
+    #[test]
+    fn test_peek_does_not_count_redirect() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+
+        assert_eq!(service.peek(slug.clone()), Ok(url.clone()));
+        assert_eq!(service.peek(slug.clone()), Ok(url.clone()));
+
+        let stats = service.get_stats(slug).unwrap();
+        assert_eq!(stats.redirects, 0);
+        assert_eq!(stats.link.url, url);
+        assert_eq!(service.peek(Slug("missing".into())), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_click_count_saturates_on_overflow() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_set_redirect_count(slug.clone(), u64::MAX - 1).unwrap();
+
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+
+        assert_eq!(service.get_stats(slug).unwrap().redirects, u64::MAX);
+    }
+
+    #[test]
+    fn test_click_count_overflow_fails_if_configured() {
+        let config = Config { fail_on_counter_overflow: true, ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_set_redirect_count(slug.clone(), u64::MAX - 1).unwrap();
+
+        service.handle_redirect(slug.clone()).unwrap();
+        assert_eq!(service.handle_redirect(slug.clone()), Err(ShortenerError::CounterOverflow));
+        assert_eq!(service.get_stats(slug).unwrap().redirects, u64::MAX);
+    }
+
+    #[test]
+    fn test_timeline_of_link() {
+        let mut service = UrlShortenerService::new();
+        let clock = MockClock::new();
+        service.set_clock(clock.clone());
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_create_short_link(Url("https://example.com".into()), Some(Slug("other".into()))).unwrap();
+        service.handle_change_short_link(slug.clone(), Url("https://example.org".into())).unwrap();
+        clock.advance(Duration::from_secs(5));
+        service.handle_redirect(slug.clone()).unwrap();
+
+        let at = SystemTime::UNIX_EPOCH;
+        assert_eq!(
+            service.timeline(slug),
+            Ok(vec![
+                TimelineEntry { kind: "LinkCreated".into(), at: Some(at) },
+                TimelineEntry { kind: "LinkUrlChanged".into(), at: None },
+                TimelineEntry { kind: "LinkRedirected".into(), at: Some(at + Duration::from_secs(5)) },
+            ]),
+        );
+        assert_eq!(service.timeline(Slug("missing".into())), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_create_unique_url_rejects_shortened_url() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+
+        let link = service.handle_create_unique_url(url.clone(), Some(Slug("first".into()))).unwrap();
+        assert_eq!(link.slug, Slug("first".into()));
+
+        assert_eq!(
+            service.handle_create_unique_url(url.clone(), None),
+            Err(ShortenerError::UrlAlreadyShortened(Slug("first".into())))
+        );
+        assert_eq!(
+            service.handle_create_unique_url(url, Some(Slug("second".into()))),
+            Err(ShortenerError::UrlAlreadyShortened(Slug("first".into())))
+        );
+    }
+
+    #[test]
+    fn test_rebuild_click_counts_repairs_drift() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_record_redirects(slug.clone(), 5).unwrap();
+        service.handle_rename_slug(slug, Slug("renamed".into())).unwrap();
+        service.handle_redirect(Slug("renamed".into())).unwrap();
+
+        // Corrupt the state directly, bypassing the event log.
+        service.click_counts.insert(Slug("renamed".into()), 100);
+        service.read_model.click_counts.clear();
+        assert!(!service.verify_consistency());
+
+        service.rebuild_click_counts();
+        assert!(service.verify_consistency());
+        assert_eq!(service.get_stats(Slug("renamed".into())).unwrap().redirects, 7);
+    }
+
+    #[test]
+    fn test_apply_events_in_batch() {
+        let slug = Slug("my_slug".into());
+        let at = SystemTime::UNIX_EPOCH;
+        let events = vec![
+            Event::LinkCreated {
+                slug: slug.clone(),
+                url: Url("https://example.com".into()),
+                meta: HashMap::new(),
+                max_redirects: None,
+                idempotency_key: None,
+                untracked: false,
+                at,
+            },
+            Event::LinkUrlChanged { slug: slug.clone(), url: Url("https://example.org".into()) },
+            Event::AliasCreated { alias: Slug("alias".into()), canonical: slug.clone() },
+            Event::LinkRedirected { slug: slug.clone(), referrer: None, country: None, at },
+            Event::RedirectsRecorded { slug: slug.clone(), count: 2 },
+        ];
+
+        let mut service = UrlShortenerService::new();
+        service.apply_events(events.clone());
+
+        assert_eq!(service.event_count(), events.len());
+        let stats = service.get_stats(Slug("alias".into())).unwrap();
+        assert_eq!(stats.link, ShortLink { slug: slug.clone(), url: Url("https://example.org".into()) });
+        assert_eq!(stats.redirects, 3);
+        assert_eq!(UrlShortenerService::from_events(events).state_fingerprint(), service.state_fingerprint());
+    }
+
+    #[test]
+    fn test_events_checksum_detects_tampering() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect_with_referrer(slug.clone(), Some("twitter.com".into())).unwrap();
+        let checksum = service.events_checksum();
+        let events: Vec<_> = service.iter_events().map(|(_, event)| event.clone()).collect();
+
+        let loaded = UrlShortenerService::from_events_with_checksum(events.clone(), checksum).unwrap();
+        assert_eq!(loaded.events_checksum(), checksum);
+
+        let mut tampered = events.clone();
+        tampered[1] = Event::RedirectsRecorded { slug, count: 1 };
+        assert_ne!(UrlShortenerService::from_events(tampered).events_checksum(), checksum);
+
+        assert!(matches!(
+            UrlShortenerService::from_events_with_checksum(events.into_iter().take(1), checksum),
+            Err(ShortenerError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_untracked_link_does_not_count_clicks() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link_tracked(url.clone(), Some(slug.clone()), false).unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(service.handle_redirect(slug.clone()).unwrap().url, url);
+        }
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 0);
+        assert_eq!(service.events_of_type(EventKind::LinkRedirected).count(), 0);
+
+        service.handle_set_click_tracking(slug.clone(), true).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 1);
+        assert!(service.verify_consistency());
+    }
+
+    #[test]
+    fn test_untracked_link_is_created_by_single_event() {
+        let mut service = UrlShortenerService::new();
+        let receiver = service.subscribe();
+        let slug = Slug("my_slug".into());
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link_tracked(url, Some(slug.clone()), false).unwrap();
+
+        let received: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(received.len(), 1);
+        assert!(matches!(&received[0], PublicEvent::LinkCreated { slug: s, untracked: true, .. } if *s == slug));
+        assert_eq!(service.events.len(), 1);
+
+        service.handle_redirect(slug.clone()).unwrap();
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 0);
+        assert!(service.verify_consistency());
+    }
+
+    #[test]
+    fn test_dedupe_reuses_only_links_with_same_tracking() {
+        let mut service = UrlShortenerService::with_config(Config { dedupe_urls: true, ..Config::default() });
+        let url = Url("https://example.com".into());
+        let tracked = service.handle_create_short_link_tracked(url.clone(), None, true).unwrap();
+        let untracked = service.handle_create_short_link_tracked(url.clone(), None, false).unwrap();
+        assert_ne!(untracked.slug, tracked.slug);
+
+        let again = service.handle_create_short_link_tracked(url.clone(), None, false).unwrap();
+        assert_eq!(again.slug, untracked.slug);
+        assert_eq!(service.handle_create_short_link(url, None).unwrap().slug, tracked.slug);
+
+        service.handle_redirect(tracked.slug.clone()).unwrap();
+        service.handle_redirect(untracked.slug.clone()).unwrap();
+        assert_eq!(service.get_stats(tracked.slug).unwrap().redirects, 1);
+        assert_eq!(service.get_stats(untracked.slug).unwrap().redirects, 0);
+    }
+
+    #[test]
+    fn test_export_stats_csv() {
+        let mut service = UrlShortenerService::new();
+        service.handle_create_short_link(Url("https://example.com/b".into()), Some(Slug("b".into()))).unwrap();
+        service
+            .handle_create_short_link(Url("https://example.com/?q=a,\"b\"".into()), Some(Slug("a".into())))
+            .unwrap();
+        service.handle_redirect(Slug("b".into())).unwrap();
+
+        assert_eq!(
+            service.export_stats_csv(),
+            "slug,url,redirects\n\
+             a,\"https://example.com/?q=a,\"\"b\"\"\",0\n\
+             b,https://example.com/b,1\n",
+        );
+    }
+
+    #[test]
+    fn test_blocked_domains_are_rejected() {
+        let config = Config { blocked_domains: HashSet::from(["Evil.com".to_string()]), ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+
+        for url in ["https://evil.com", "https://x.EVIL.com/path", "http://user@evil.com:8080"] {
+            assert_eq!(
+                service.handle_create_short_link(Url(url.into()), None),
+                Err(ShortenerError::DomainBlocked),
+            );
+        }
+        for url in ["https://notevil.com", "https://evil.com.example.org", "https://example.com/evil.com"] {
+            assert!(service.handle_create_short_link(Url(url.into()), None).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_fold_events() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_change_short_link(slug.clone(), Url("https://example.org".into())).unwrap();
+        service.handle_redirect(slug).unwrap();
+
+        assert_eq!(service.fold_events(0, |count, _| count + 1), 4);
+        let redirects = service.fold_events(0, |count, event| {
+            count + usize::from(event.kind() == EventKind::LinkRedirected)
+        });
+        assert_eq!(redirects, 2);
+
+        let kinds = service.fold_events(Vec::new(), |mut kinds, event| {
+            kinds.push(event.kind());
+            kinds
+        });
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::LinkCreated,
+                EventKind::LinkRedirected,
+                EventKind::LinkUrlChanged,
+                EventKind::LinkRedirected,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_change_with_new_slug_rotates_slug() {
+        let mut service = UrlShortenerService::new();
+        service.set_slug_generator(SequenceSlugGenerator::new(vec!["old", "new"]));
+        let new_url = Url("https://example.org".into());
+        let old = service.handle_create_short_link(Url("https://example.com".into()), None).unwrap().slug;
+        service.handle_redirect(old.clone()).unwrap();
+
+        let link = service.handle_change_with_new_slug(old.clone(), new_url.clone()).unwrap();
+
+        assert_eq!(link, ShortLink { slug: Slug("new".into()), url: new_url.clone() });
+        assert_eq!(service.handle_redirect(old.clone()), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.handle_redirect(link.slug.clone()).unwrap().url, new_url);
+        assert_eq!(service.get_stats(link.slug).unwrap().redirects, 1);
+        assert!(service.verify_consistency());
+    }
+
+    #[test]
+    fn test_change_with_new_slug_validates_input() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+
+        assert_eq!(
+            service.handle_change_with_new_slug(Slug("missing".into()), Url("https://example.org".into())),
+            Err(ShortenerError::SlugNotFound)
+        );
+        assert_eq!(
+            service.handle_change_with_new_slug(slug.clone(), Url("invalid".into())),
+            Err(ShortenerError::InvalidUrl)
+        );
+        assert!(service.handle_redirect(slug).is_ok());
+    }
+
+    #[test]
+    fn test_global_throttle_drains_and_refills() {
+        let throttle = GlobalThrottle { capacity: 2, refill_every: Duration::from_secs(10) };
+        let mut service = UrlShortenerService::with_config(Config { global_throttle: Some(throttle), ..Config::default() });
+        let clock = MockClock::new();
+        service.set_clock(clock.clone());
+        let first = Slug("first".into());
+        let second = Slug("second".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(first.clone())).unwrap();
+        service.handle_create_short_link(Url("https://example.org".into()), Some(second.clone())).unwrap();
+
+        service.handle_redirect(first.clone()).unwrap();
+        service.handle_redirect(second.clone()).unwrap();
+        assert_eq!(service.handle_redirect(first.clone()), Err(ShortenerError::RateLimited));
+        assert_eq!(service.handle_redirect(second.clone()), Err(ShortenerError::RateLimited));
+
+        clock.advance(Duration::from_secs(15));
+        service.handle_redirect(first.clone()).unwrap();
+        assert_eq!(service.handle_redirect(second.clone()), Err(ShortenerError::RateLimited));
+
+        clock.advance(Duration::from_secs(5));
+        service.handle_redirect(second.clone()).unwrap();
+
+        clock.advance(Duration::from_secs(100));
+        service.handle_redirect(first.clone()).unwrap();
+        service.handle_redirect(second).unwrap();
+        assert_eq!(service.handle_redirect(first.clone()), Err(ShortenerError::RateLimited));
+        assert_eq!(service.get_stats(first).unwrap().redirects, 3);
+    }
+
+    #[test]
+    fn test_detailed_errors_carry_input() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let missing = Slug("missing".into());
+        service.try_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+
+        assert_eq!(
+            service.try_create_short_link(Url("https://example.org".into()), Some(slug.clone())),
+            Err(DetailedError::SlugAlreadyInUse(slug)),
+        );
+        assert_eq!(
+            service.try_create_short_link(Url("invalid".into()), None),
+            Err(DetailedError::InvalidUrl(Url("invalid".into()))),
+        );
+        assert_eq!(
+            service.try_create_short_link(Url("https://example.org".into()), Some(Slug("bad slug".into()))),
+            Err(DetailedError::Other(ShortenerError::InvalidSlug)),
+        );
+        assert_eq!(service.try_redirect(missing.clone()), Err(DetailedError::SlugNotFound(missing.clone())));
+        let err = service.try_get_stats(missing.clone()).unwrap_err();
+        assert_eq!(err.to_string(), "slug `missing` not found");
+        assert_eq!(ShortenerError::from(err), ShortenerError::SlugNotFound);
+    }
+
+    #[test]
+    fn test_redirect_resolved_follows_chain() {
+        let mut service = UrlShortenerService::builder().base_url("https://sho.rt/").build().unwrap();
+        service
+            .handle_create_short_link(Url("https://example.com".into()), Some(Slug("c".into())))
+            .unwrap();
+        service
+            .handle_create_short_link(Url("https://sho.rt/c".into()), Some(Slug("b".into())))
+            .unwrap();
+        service
+            .handle_create_short_link(Url("https://sho.rt/b/".into()), Some(Slug("a".into())))
+            .unwrap();
+
+        assert_eq!(
+            service.handle_redirect_resolved(Slug("a".into()), 1),
+            Err(ShortenerError::RedirectLoop),
+        );
+        let link = service.handle_redirect_resolved(Slug("a".into()), 2).unwrap();
+        assert_eq!(link, ShortLink { slug: Slug("c".into()), url: Url("https://example.com".into()) });
+        for slug in ["a", "b", "c"] {
+            assert_eq!(service.get_stats(Slug(slug.into())).unwrap().redirects, 1);
+        }
+    }
+
+    #[test]
+    fn test_redirect_resolved_detects_loop() {
+        let mut service = UrlShortenerService::builder().base_url("https://sho.rt/").build().unwrap();
+        service
+            .handle_create_short_link(Url("https://sho.rt/self".into()), Some(Slug("self".into())))
+            .unwrap();
+
+        assert_eq!(
+            service.handle_redirect_resolved(Slug("self".into()), 10),
+            Err(ShortenerError::RedirectLoop),
+        );
+        assert_eq!(service.get_stats(Slug("self".into())).unwrap().redirects, 0);
+    }
+
+    #[test]
+    fn test_description_latest_wins_and_survives_replay() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("docs".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        assert_eq!(service.get_description(slug.clone()), Ok(None));
+
+        service.handle_set_description(slug.clone(), "first".into()).unwrap();
+        service.handle_set_description(slug.clone(), "second".into()).unwrap();
+        assert_eq!(service.get_description(slug.clone()), Ok(Some("second".into())));
+
+        let replayed = UrlShortenerService::from_events(service.events.clone());
+        assert_eq!(replayed.get_description(slug), Ok(Some("second".into())));
+
+        let missing = Slug("missing".into());
+        assert_eq!(
+            service.handle_set_description(missing.clone(), "text".into()),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert_eq!(service.get_description(missing), Err(ShortenerError::SlugNotFound));
+    }
+
+    #[test]
+    fn test_short_link_stats_round_trip() {
+        let link = ShortLink { slug: Slug("abc".into()), url: Url("https://example.com".into()) };
+
+        let stats = Stats::from(link.clone());
+        assert_eq!(stats.redirects, 0);
+        assert_eq!(stats.into_link(), link);
+    }
+
+    #[test]
+    fn test_delete_where_removes_unclicked_links() {
+        let mut service = UrlShortenerService::new();
+        for slug in ["clicked", "idle1", "idle2"] {
+            service
+                .handle_create_short_link(Url(format!("https://example.com/{slug}")), Some(Slug(slug.into())))
+                .unwrap();
+        }
+        service.handle_redirect(Slug("clicked".into())).unwrap();
+
+        assert_eq!(service.delete_where(|_, _, redirects| redirects == 0), 2);
+        assert!(service.get_stats(Slug("clicked".into())).is_ok());
+        assert_eq!(service.get_stats(Slug("idle1".into())), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.get_stats(Slug("idle2".into())), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.delete_where(|_, url, _| url.0.contains("missing")), 0);
+    }
+
+    #[test]
+    fn test_immutable_links_reject_changes() {
+        let config = Config { immutable_links: true, ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let slug = Slug("fixed".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+
+        assert_eq!(
+            service.handle_change_short_link(slug.clone(), Url("https://example.org".into())),
+            Err(ShortenerError::LinkImmutable),
+        );
+        assert_eq!(service.peek(slug.clone()), Ok(Url("https://example.com".into())));
+
+        let mut service = UrlShortenerService::new();
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        assert!(service.handle_change_short_link(slug.clone(), Url("https://example.org".into())).is_ok());
+        assert_eq!(service.peek(slug), Ok(Url("https://example.org".into())));
+    }
+
+    #[test]
+    fn test_validate_event_log() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("abc".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_create_alias(slug.clone(), Slug("alias".into())).unwrap();
+        service.handle_redirect(Slug("alias".into())).unwrap();
+        service.handle_change_short_link(slug.clone(), Url("https://example.org".into())).unwrap();
+        assert_eq!(service.validate_event_log(), Ok(()));
+
+        service.events = vec![
+            Event::LinkCreated {
+                slug: Slug("other".into()),
+                url: Url("https://example.com".into()),
+                meta: HashMap::new(),
+                max_redirects: None,
+                idempotency_key: None,
+                untracked: false,
+                at: SystemTime::UNIX_EPOCH,
+            },
+            Event::LinkRedirected {
+                slug: slug.clone(),
+                referrer: None,
+                country: None,
+                at: SystemTime::UNIX_EPOCH,
+            },
+        ];
+        let err = service.validate_event_log().unwrap_err();
+        assert_eq!(err, LogError { index: 1, kind: EventKind::LinkRedirected });
+        assert_eq!(err.to_string(), "dangling LinkRedirected event at index 1");
+    }
+
+    #[test]
+    fn test_tail_events() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("abc".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        for _ in 0..3 {
+            service.handle_redirect(slug.clone()).unwrap();
+        }
+        service.handle_change_short_link(slug, Url("https://example.org".into())).unwrap();
+
+        let tail: Vec<_> = service.tail_events(3).into_iter().map(Event::kind).collect();
+        assert_eq!(
+            tail,
+            [EventKind::LinkRedirected, EventKind::LinkRedirected, EventKind::LinkUrlChanged],
+        );
+        assert_eq!(service.tail_events(100).len(), 5);
+        assert!(service.tail_events(0).is_empty());
+    }
+
+    #[test]
+    fn test_clock_drives_event_timestamps() {
+        let mut service = UrlShortenerService::new();
+        let clock = MockClock::new();
+        service.set_clock(clock.clone());
+        let slug = Slug("abc".into());
+
+        clock.advance(Duration::from_secs(10));
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        clock.advance(Duration::from_secs(5));
+        service.handle_redirect(slug.clone()).unwrap();
+
+        let at: Vec<_> = service.timeline(slug).unwrap().into_iter().map(|entry| entry.at).collect();
+        let epoch = SystemTime::UNIX_EPOCH;
+        assert_eq!(
+            at,
+            [Some(epoch + Duration::from_secs(10)), Some(epoch + Duration::from_secs(15))],
+        );
+    }
+
+    #[test]
+    fn test_state_at_shows_past_url() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("abc".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_change_short_link(slug.clone(), Url("https://example.org".into())).unwrap();
+
+        let stats = service.state_at(1).get_stats(slug.clone()).unwrap();
+        assert_eq!(stats.link.url, Url("https://example.com".into()));
+        assert_eq!(stats.redirects, 1);
+        assert_eq!(service.state_at(0).get_stats(slug.clone()).unwrap().redirects, 0);
+        assert_eq!(
+            service.state_at(100).get_stats(slug).unwrap().link.url,
+            Url("https://example.org".into()),
+        );
+    }
+
+    #[test]
+    fn test_redirect_debounce() {
+        let config = Config { redirect_debounce: Some(Duration::from_secs(2)), ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let clock = MockClock::new();
+        service.set_clock(clock.clone());
+        let slug = Slug("abc".into());
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+
+        service.handle_redirect(slug.clone()).unwrap();
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(service.handle_redirect(slug.clone()).unwrap().url, url);
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 1);
+        assert_eq!(service.event_counts_for(&slug), (1, 1));
+
+        clock.advance(Duration::from_secs(1));
+        service.handle_redirect(slug.clone()).unwrap();
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 2);
+    }
+
+    #[test]
+    fn test_unused_links() {
+        let mut service = UrlShortenerService::new();
+        for slug in ["d", "c", "b", "a"] {
+            service
+                .handle_create_short_link(Url(format!("https://example.com/{slug}")), Some(Slug(slug.into())))
+                .unwrap();
+        }
+        service.handle_redirect(Slug("a".into())).unwrap();
+        service.handle_redirect(Slug("c".into())).unwrap();
+
+        let unused: Vec<_> = service.unused_links().into_iter().map(|link| link.slug.0).collect();
+        assert_eq!(unused, ["b", "d"]);
+    }
+
+    #[test]
+    fn test_preview_random_slug() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let service = UrlShortenerService::builder().slug_length(12).slug_alphabet("xyz").build().unwrap();
+        let first = service.preview_random_slug();
+        assert_eq!(first.0.len(), 12);
+        assert!(first.0.chars().all(|c| "xyz".contains(c)));
+        assert_ne!(service.preview_random_slug(), first);
+        assert_eq!(service.event_count(), 0);
+
+        let seeded = UrlShortenerService::with_rng(StdRng::seed_from_u64(7));
+        let same_seeded = UrlShortenerService::with_rng(StdRng::seed_from_u64(7));
+        let previews: Vec<_> = (0..3).map(|_| seeded.preview_random_slug()).collect();
+        assert_ne!(previews[0], previews[1]);
+        assert_ne!(previews[1], previews[2]);
+        assert_eq!(same_seeded.preview_random_slug(), previews[0]);
+    }
+
+    #[test]
+    fn test_subscribers_receive_appended_events() {
+        let mut service = UrlShortenerService::new();
+        let receiver = service.subscribe();
+        let dropped = service.subscribe();
+        drop(dropped);
+        let slug = Slug("abc".into());
+        let url = Url("https://example.com".into());
+
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+
+        let received: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(received.len(), 2);
+        assert!(matches!(&received[0], PublicEvent::LinkCreated { slug: s, url: u, .. } if *s == slug && *u == url));
+        assert!(matches!(&received[1], PublicEvent::LinkRedirected { slug: s, .. } if *s == slug));
+        assert_eq!(service.subscribers.len(), 1);
+    }
+
+    #[test]
+    fn test_truncate_slugs() {
+        let config = Config { truncate_slugs_to: Some(4), ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        service.set_slug_generator(SequenceSlugGenerator::new(vec!["longa", "longb", "other"]));
+        let url = Url("https://example.com".into());
+
+        let link = service.handle_create_short_link(url.clone(), Some(Slug("longslug".into()))).unwrap();
+        assert_eq!(link.slug, Slug("long".into()));
+        assert_eq!(service.get_stats(Slug("long".into())).unwrap().link.url, url);
+        assert_eq!(service.get_stats(Slug("longslug".into())), Err(ShortenerError::SlugNotFound));
+        assert_eq!(
+            service.handle_create_short_link(url.clone(), Some(Slug("longer".into()))),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+
+        let generated = service.handle_create_short_link(url, None).unwrap();
+        assert_eq!(generated.slug, Slug("othe".into()));
+        assert_eq!(service.slug_collision_count(), 2);
+
+        let builder = UrlShortenerService::builder().truncate_slugs_to(0);
+        assert!(matches!(builder.build(), Err(ShortenerError::InvalidConfig)));
+    }
+
+    #[test]
+    fn test_replace_event_log() {
+        let mut source = UrlShortenerService::new();
+        let slug = Slug("abc".into());
+        source.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        source.handle_redirect(slug.clone()).unwrap();
+
+        let mut service = UrlShortenerService::new();
+        service.handle_create_short_link(Url("https://example.org".into()), Some(Slug("old".into()))).unwrap();
+        service.replace_event_log(source.events.clone()).unwrap();
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 1);
+        assert_eq!(service.get_stats(Slug("old".into())), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.event_count(), 2);
+        assert!(service.verify_consistency());
+
+        let mut invalid = source.events.clone();
+        invalid.swap(0, 1);
+        assert_eq!(
+            service.replace_event_log(invalid),
+            Err(LogError { index: 0, kind: EventKind::LinkRedirected }),
+        );
+        assert_eq!(service.events, source.events);
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 1);
+    }
+
+    #[test]
+    fn test_swap_urls() {
+        let mut service = UrlShortenerService::new();
+        let (a, b) = (Slug("a".into()), Slug("b".into()));
+        let (url_a, url_b) = (Url("https://example.com/a".into()), Url("https://example.com/b".into()));
+        service.handle_create_short_link(url_a.clone(), Some(a.clone())).unwrap();
+        service.handle_create_short_link(url_b.clone(), Some(b.clone())).unwrap();
+        service.handle_redirect(a.clone()).unwrap();
+
+        service.handle_swap_urls(a.clone(), b.clone()).unwrap();
+        let stats_a = service.get_stats(a.clone()).unwrap();
+        assert_eq!((stats_a.link.url, stats_a.redirects), (url_b.clone(), 1));
+        let stats_b = service.get_stats(b.clone()).unwrap();
+        assert_eq!((stats_b.link.url, stats_b.redirects), (url_a, 0));
+        assert_eq!(service.url_index[&url_b], vec![a.clone()]);
+
+        let events = service.event_count();
+        assert_eq!(service.handle_swap_urls(a.clone(), a.clone()), Ok(()));
+        assert_eq!(service.event_count(), events);
+        assert_eq!(
+            service.handle_swap_urls(a, Slug("missing".into())),
+            Err(ShortenerError::SlugNotFound),
+        );
+        assert_eq!(service.event_count(), events);
+    }
+
+    #[test]
+    fn test_distinct_hosts() {
+        let mut service = UrlShortenerService::new();
+        for url in ["https://example.com/a", "https://EXAMPLE.com/b", "https://example.org"] {
+            service.handle_create_short_link(Url(url.into()), None).unwrap();
+        }
+        service.apply_events([Event::LinkCreated {
+            slug: Slug("broken".into()),
+            url: Url("not a url".into()),
+            meta: HashMap::new(),
+            max_redirects: None,
+            idempotency_key: None,
+            untracked: false,
+            at: SystemTime::UNIX_EPOCH,
+        }]);
+
+        assert_eq!(service.distinct_hosts(), 2);
+    }
+
+    #[test]
+    fn test_reset_clicks() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("campaign".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        for _ in 0..3 {
+            service.handle_redirect(slug.clone()).unwrap();
+        }
+
+        service.handle_reset_clicks(slug.clone()).unwrap();
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 0);
+        service.handle_redirect(slug.clone()).unwrap();
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 1);
+
+        let replayed = UrlShortenerService::from_events(service.events.clone());
+        assert_eq!(replayed.get_stats(slug).unwrap().redirects, 1);
+        assert_eq!(
+            service.handle_reset_clicks(Slug("missing".into())),
+            Err(ShortenerError::SlugNotFound),
+        );
+    }
+
+    #[test]
+    fn test_redirect_guard_denies_redirects() {
+        let mut service = UrlShortenerService::new();
+        let blocked = Arc::new(Mutex::new(HashSet::from([Slug("denied".into())])));
+        let guard_blocked = Arc::clone(&blocked);
+        service.set_redirect_guard(move |slug, _| !guard_blocked.lock().unwrap().contains(slug));
+        for slug in ["denied", "allowed"] {
+            service
+                .handle_create_short_link(Url("https://example.com".into()), Some(Slug(slug.into())))
+                .unwrap();
+        }
+
+        assert_eq!(service.handle_redirect(Slug("denied".into())), Err(ShortenerError::RedirectDenied));
+        assert!(service.handle_redirect(Slug("allowed".into())).is_ok());
+        assert_eq!(service.get_stats(Slug("denied".into())).unwrap().redirects, 0);
+        assert_eq!(service.get_stats(Slug("allowed".into())).unwrap().redirects, 1);
+
+        blocked.lock().unwrap().clear();
+        assert!(service.handle_redirect(Slug("denied".into())).is_ok());
+    }
+
+    #[test]
+    fn test_event_at_offset_from_end() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("abc".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug).unwrap();
+
+        let kind = |offset| service.event_at_offset_from_end(offset).map(Event::kind);
+        assert_eq!(kind(0), Some(EventKind::LinkRedirected));
+        assert_eq!(kind(1), Some(EventKind::LinkCreated));
+        assert_eq!(kind(2), None);
+    }
+
+    #[test]
+    fn test_slug_prefix_applies_to_generated_slugs() {
+        let config = Config { slug_prefix: "g-".into(), ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        service.set_slug_generator(SequenceSlugGenerator::new(vec!["taken", "taken", "fresh"]));
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link(url.clone(), Some(Slug("g-taken".into()))).unwrap();
+
+        let generated = service.handle_create_short_link(url.clone(), None).unwrap();
+        assert_eq!(generated.slug, Slug("g-fresh".into()));
+        assert_eq!(service.slug_collision_count(), 2);
+
+        let custom = service.handle_create_short_link(url, Some(Slug("custom".into()))).unwrap();
+        assert_eq!(custom.slug, Slug("custom".into()));
+    }
+
+    #[test]
+    fn test_redirects_by_host() {
+        let mut service = UrlShortenerService::new();
+        let links = [
+            ("a", "https://example.com/a", 2),
+            ("b", "https://Example.com/b", 3),
+            ("c", "https://example.org", 1),
+            ("d", "https://example.net", 0),
+        ];
+        for (slug, url, redirects) in links {
+            service.handle_create_short_link(Url(url.into()), Some(Slug(slug.into()))).unwrap();
+            for _ in 0..redirects {
+                service.handle_redirect(Slug(slug.into())).unwrap();
+            }
+        }
+
+        let expected = HashMap::from([
+            ("example.com".to_owned(), 5),
+            ("example.org".to_owned(), 1),
+            ("example.net".to_owned(), 0),
+        ]);
+        assert_eq!(service.redirects_by_host(), expected);
+    }
+
+    #[test]
+    fn test_diff_against_replay() {
+        let mut service = UrlShortenerService::new();
+        for slug in ["same", "changed", "clicked", "current"] {
+            let url = Url(format!("https://example.com/{slug}"));
+            service.handle_create_short_link(url, Some(Slug(slug.into()))).unwrap();
+        }
+        let mut events = service.events.clone();
+        assert!(service.diff_against_replay(&events).is_empty());
+
+        service.handle_redirect(Slug("clicked".into())).unwrap();
+        events.retain(|event| {
+            !matches!(event, Event::LinkCreated { slug, .. } if slug.0 == "current")
+        });
+        events.push(Event::LinkUrlChanged {
+            slug: Slug("changed".into()),
+            url: Url("https://example.org".into()),
+        });
+        let mut replay_only = UrlShortenerService::new();
+        replay_only
+            .handle_create_short_link(Url("https://example.net".into()), Some(Slug("replay".into())))
+            .unwrap();
+        events.extend(replay_only.events);
+
+        let diff = service.diff_against_replay(&events);
+        assert_eq!(
+            diff,
+            ReplayDiff {
+                only_current: vec![Slug("current".into())],
+                only_replay: vec![Slug("replay".into())],
+                different_urls: vec![Slug("changed".into())],
+                different_counts: vec![Slug("clicked".into())],
+            },
+        );
+        assert_eq!(service.event_count(), 5);
+    }
+
+    #[test]
+    fn test_iter_stats_is_lazy() {
+        let mut service = UrlShortenerService::new();
+        for slug in ["a", "b", "c", "d"] {
+            service
+                .handle_create_short_link(Url(format!("https://example.com/{slug}")), Some(Slug(slug.into())))
+                .unwrap();
+        }
+        service.handle_redirect(Slug("b".into())).unwrap();
+        service.handle_redirect(Slug("d".into())).unwrap();
+
+        assert_eq!(service.iter_stats().take(2).count(), 2);
+        let mut clicked: Vec<_> = service
+            .iter_stats()
+            .filter(|stats| stats.redirects >= 1)
+            .map(|stats| stats.link.slug.0)
+            .collect();
+        clicked.sort();
+        assert_eq!(clicked, ["b", "d"]);
+    }
+
+    #[test]
+    fn test_event_sink_persists_appended_events() {
+        struct VecSink(Arc<Mutex<Vec<PublicEvent>>>);
+
+        impl EventSink for VecSink {
+            fn persist(&mut self, event: &PublicEvent) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+
+        let mut service = UrlShortenerService::new();
+        let persisted = Arc::new(Mutex::new(Vec::new()));
+        service.set_event_sink(VecSink(Arc::clone(&persisted)));
+        let slug = Slug("abc".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
+        service.handle_change_short_link(slug, Url("https://example.org".into())).unwrap();
+
+        assert_eq!(*persisted.lock().unwrap(), service.export_public_events());
     }
 
-    /// Creates a LinkRedirected event and adds it to the list of events
-    fn add_link_redirected_event(&mut self, slug: &Slug) {
-        self.events.push(Event::LinkRedirected { slug: slug.clone() });
-        *self.click_counts.entry(slug.clone()).or_insert(0) += 1;
+    #[test]
+    fn test_relative_urls_are_joined_with_base() {
+        let mut service =
+            UrlShortenerService::builder().resolve_relative("https://app.example.com/").build().unwrap();
+        let relative = Slug("docs".into());
+        let absolute = Slug("external".into());
+        service.handle_create_short_link(Url("/docs?page=1".into()), Some(relative.clone())).unwrap();
+        service.handle_create_short_link(Url("https://example.org/x".into()), Some(absolute.clone())).unwrap();
+
+        assert_eq!(
+            service.handle_redirect(relative.clone()).unwrap().url,
+            Url("https://app.example.com/docs?page=1".into()),
+        );
+        assert_eq!(service.peek(relative), Ok(Url("/docs?page=1".into())));
+        assert_eq!(service.handle_redirect(absolute).unwrap().url, Url("https://example.org/x".into()));
+
+        let mut strict = UrlShortenerService::new();
+        assert_eq!(
+            strict.handle_create_short_link(Url("/docs".into()), None),
+            Err(ShortenerError::InvalidUrl),
+        );
+        assert_eq!(
+            service.handle_create_short_link(Url("//example.org".into()), None),
+            Err(ShortenerError::InvalidUrl),
+        );
     }
-}
 
-impl commands::CommandHandler for UrlShortenerService {
-    fn handle_create_short_link(
-        &mut self,
-        url: Url,
-        slug: Option<Slug>,
-    ) -> Result<ShortLink, ShortenerError> {
-        let slug = match slug {
-            Some(custom_slug) => {
-                if self.links.contains_key(&custom_slug) {
-                    return Err(ShortenerError::SlugAlreadyInUse)
-                }
-                custom_slug
-            }
-            None => {
-                let mut generated_slug = Self::generate_random_slug();
-                while self.links.contains_key(&generated_slug) {
-                    generated_slug = Self::generate_random_slug();
-                }
-                generated_slug
-            }
-        };
+    #[test]
+    fn test_create_with_stats_reports_collisions() {
+        use rand::{SeedableRng, rngs::StdRng};
 
-        self.add_link_created_event(slug.clone(), url.clone());
+        let twin = UrlShortenerService::with_rng(StdRng::seed_from_u64(3));
+        let mut service = UrlShortenerService::with_rng(StdRng::seed_from_u64(3));
+        let url = Url("https://example.com".into());
+        for _ in 0..2 {
+            let taken = twin.preview_random_slug();
+            let (_, retries) = service.handle_create_short_link_with_stats(url.clone(), Some(taken)).unwrap();
+            assert_eq!(retries, 0);
+        }
+        let expected = twin.preview_random_slug();
 
-        Ok(ShortLink { slug, url})
+        let (link, retries) = service.handle_create_short_link_with_stats(url.clone(), None).unwrap();
+        assert_eq!((link.slug, retries), (expected, 2));
+        let (_, retries) = service.handle_create_short_link_with_stats(url, None).unwrap();
+        assert_eq!(retries, 0);
     }
 
-    fn handle_redirect(
-        &mut self,
-        slug: Slug,
-    ) -> Result<ShortLink, ShortenerError> {
-        let url = self.links.get(&slug).ok_or(ShortenerError::SlugNotFound)?.clone();
+    #[test]
+    fn test_redirect_distribution() {
+        let mut service = UrlShortenerService::new();
+        assert_eq!(service.redirect_distribution(), Distribution::default());
 
-        self.add_link_redirected_event(&slug);
+        for (i, count) in [5, 0, 100, 3, 8, 1, 2, 4, 7, 6].into_iter().enumerate() {
+            let slug = Slug(format!("s{i}"));
+            service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+            service.handle_record_redirects(slug, count).unwrap();
+        }
 
-        Ok(ShortLink { slug, url })
+        assert_eq!(
+            service.redirect_distribution(),
+            Distribution { min: 0, max: 100, median: 4, p95: 100 },
+        );
     }
 
-    fn handle_change_short_link(
-        &mut self,
-        slug: Slug,
-        new_url: Url,
-    ) -> Result<ShortLink, ShortenerError> {
-        if !self.links.contains_key(&slug) {
-            return Err(ShortenerError::SlugNotFound);
+    #[test]
+    fn test_idempotent_identical_create() {
+        let config = Config { idempotent_identical_create: true, ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let slug = Slug("abc".into());
+        let url = Url("https://example.com".into());
+        let link = service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+
+        assert_eq!(service.handle_create_short_link(url.clone(), Some(slug.clone())), Ok(link));
+        assert_eq!(service.event_count(), 1);
+        assert_eq!(
+            service.handle_create_short_link(Url("https://example.org".into()), Some(slug.clone())),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+
+        let mut strict = UrlShortenerService::new();
+        strict.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+        assert_eq!(
+            strict.handle_create_short_link(url, Some(slug)),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+    }
+
+    #[test]
+    fn test_replay_strict() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("abc".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_create_alias(slug.clone(), Slug("alias".into())).unwrap();
+        service.handle_redirect(Slug("alias".into())).unwrap();
+        service.handle_delete_short_link(Slug("alias".into())).unwrap();
+
+        let replayed = UrlShortenerService::replay_strict(&service.events).unwrap();
+        assert_eq!(replayed.get_stats(slug.clone()).unwrap().redirects, 1);
+
+        let mut malformed = service.events.clone();
+        malformed.insert(2, malformed[0].clone());
+        assert!(matches!(
+            UrlShortenerService::replay_strict(&malformed),
+            Err((2, ShortenerError::SlugAlreadyInUse)),
+        ));
+
+        let mut malformed = service.events.clone();
+        malformed.swap(0, 2);
+        assert!(matches!(
+            UrlShortenerService::replay_strict(&malformed),
+            Err((0, ShortenerError::SlugNotFound)),
+        ));
+    }
+
+    #[test]
+    fn test_exhausted_slug_space_fails() {
+        let mut service = UrlShortenerService::builder().slug_length(1).slug_alphabet("a").build().unwrap();
+        let url = Url("https://example.com".into());
+
+        let link = service.handle_create_short_link(url.clone(), None).unwrap();
+        assert_eq!(link.slug, Slug("a".into()));
+        assert_eq!(
+            service.handle_create_short_link(url.clone(), None),
+            Err(ShortenerError::SlugSpaceExhausted),
+        );
+        assert_eq!(
+            service.handle_change_with_new_slug(link.slug.clone(), url),
+            Err(ShortenerError::SlugSpaceExhausted),
+        );
+        assert!(service.get_stats(link.slug).is_ok());
+        assert_eq!(service.slug_collision_count(), MAX_SLUG_ATTEMPTS as u64 * 2);
+    }
+
+    #[test]
+    fn test_invalid_config_is_rejected() {
+        let config = Config { truncate_slugs_to: Some(0), ..Config::default() };
+        assert!(matches!(
+            UrlShortenerService::try_with_config(config.clone()),
+            Err(ShortenerError::InvalidConfig),
+        ));
+        let config = Config { allowed_schemes: vec![], ..Config::default() };
+        assert_eq!(config.validate(), Err(ShortenerError::InvalidConfig));
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid configuration")]
+    fn test_with_config_panics_on_invalid_config() {
+        UrlShortenerService::with_config(Config { truncate_slugs_to: Some(0), ..Config::default() });
+    }
+
+    #[test]
+    fn test_summary_saturates_total_redirects() {
+        let mut service = UrlShortenerService::new();
+        for slug in ["a", "b"] {
+            let slug = Slug(slug.into());
+            service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+            service.handle_set_redirect_count(slug, u64::MAX).unwrap();
         }
 
-        self.add_link_created_event(slug.clone(), new_url.clone());
+        assert_eq!(service.summary().total_redirects, u64::MAX);
+    }
 
-        Ok(ShortLink { slug, url: new_url })
+    #[test]
+    fn test_failed_commit_keeps_reservation() {
+        let config = Config { max_links: Some(1), ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let url = Url("https://example.com".into());
+        let other = Slug("other".into());
+        let slug = Slug("my_slug".into());
+
+        service.handle_create_short_link(url.clone(), Some(other.clone())).unwrap();
+        service.reserve(slug.clone(), Duration::from_secs(60)).unwrap();
+        assert_eq!(service.commit_reserved(slug.clone(), url.clone()), Err(ShortenerError::LinkLimitReached));
+        assert_eq!(service.reserve(slug.clone(), Duration::from_secs(60)), Err(ShortenerError::SlugAlreadyInUse));
+
+        service.handle_delete_short_link(other).unwrap();
+        let link = service.commit_reserved(slug.clone(), url.clone()).unwrap();
+
+        assert_eq!(link, ShortLink { slug, url });
     }
-}
 
-impl queries::QueryHandler for UrlShortenerService {
-    fn get_stats(&self, slug: Slug) -> Result<Stats, ShortenerError> {
-        let url = self.links.get(&slug).ok_or(ShortenerError::SlugNotFound)?.clone();
-        let redirects = *self.click_counts.get(&slug).unwrap_or(&0);
+    #[test]
+    fn test_merge_over_link_limit_fails_without_changes() {
+        let config = Config { max_links: Some(2), ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let mut other = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link(url.clone(), Some(Slug("shared".into()))).unwrap();
+        other.handle_create_short_link(url.clone(), Some(Slug("shared".into()))).unwrap();
+        other.handle_create_short_link(url.clone(), Some(Slug("a".into()))).unwrap();
+        other.handle_create_short_link(url.clone(), Some(Slug("b".into()))).unwrap();
 
-        Ok(Stats {
-            link: ShortLink { slug, url },
-            redirects
-        })
+        assert_eq!(service.merge(other), Err(DetailedError::Other(ShortenerError::LinkLimitReached)));
+        assert_eq!(service.get_stats(Slug("a".into())), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.event_count(), 1);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::commands::CommandHandler;
-    use crate::queries::QueryHandler;
+    #[test]
+    fn test_validate_create_trims_slug_like_create() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+
+        assert_eq!(service.validate_create(&url, Some(&Slug(" my ".into()))), Ok(()));
+        service.handle_create_short_link(url.clone(), Some(Slug("my".into()))).unwrap();
+        assert_eq!(
+            service.validate_create(&url, Some(&Slug(" my ".into()))),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+    }
 
     #[test]
-    fn test_random_slug_generation_creates_unique_link() {
+    fn test_validate_create_truncates_slug_like_create() {
+        let config = Config { truncate_slugs_to: Some(3), ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link(url.clone(), Some(Slug("abc".into()))).unwrap();
+
+        assert_eq!(
+            service.validate_create(&url, Some(&Slug("abcdef".into()))),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+    }
+
+    #[test]
+    fn test_validate_create_respects_link_limit() {
+        let config = Config { max_links: Some(1), ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link(url.clone(), None).unwrap();
+
+        assert_eq!(service.validate_create(&url, None), Err(ShortenerError::LinkLimitReached));
+        assert_eq!(
+            service.validate_create(&url, Some(&Slug("free".into()))),
+            Err(ShortenerError::LinkLimitReached),
+        );
+    }
+
+    #[test]
+    fn test_validate_create_accepts_idempotent_identical_create() {
+        let config = Config { idempotent_identical_create: true, ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let url = Url("https://example.com".into());
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+
+        assert_eq!(service.validate_create(&url, Some(&slug)), Ok(()));
+        assert_eq!(
+            service.validate_create(&Url("https://example.org".into()), Some(&slug)),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+    }
+
+    #[test]
+    fn test_idempotency_key_of_deleted_link_creates_new_link() {
         let mut service = UrlShortenerService::new();
-        let url = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF8".to_string());
+        let url = Url("https://example.com".into());
+        let slug = Slug("my_slug".into());
+        service.handle_create_idempotent("k".into(), url.clone(), Some(slug.clone())).unwrap();
+        service.handle_delete_short_link(slug.clone()).unwrap();
 
-        let result = service.handle_create_short_link(url.clone(), None);
-        assert!(result.is_ok());
+        let link = service.handle_create_idempotent("k".into(), url.clone(), Some(slug.clone())).unwrap();
 
-        let short_link = result.unwrap();
-        assert_eq!(short_link.url, url);
-        assert_eq!(service.links.len(), 1);
+        assert_eq!(link, ShortLink { slug: slug.clone(), url });
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 0);
+        assert!(service.verify_consistency());
     }
 
     #[test]
-    fn test_custom_slug_allows_exact_alias_creation() {
+    fn test_idempotency_key_follows_renamed_slug() {
         let mut service = UrlShortenerService::new();
-        let url = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF8".to_string());
-        let slug = Slug("my_slug".to_string());
+        let url = Url("https://example.com".into());
+        service.handle_create_idempotent("k".into(), url.clone(), Some(Slug("old".into()))).unwrap();
+        service.handle_rename_slug(Slug("old".into()), Slug("new".into())).unwrap();
 
-        let result = service.handle_create_short_link(url.clone(), Some(slug.clone()));
-        assert!(result.is_ok());
+        let link = service.handle_create_idempotent("k".into(), url.clone(), None).unwrap();
 
-        let short_link = result.unwrap();
-        assert_eq!(short_link.slug, slug);
-        assert_eq!(short_link.url, url);
-        assert_eq!(service.links.len(), 1);
+        assert_eq!(link, ShortLink { slug: Slug("new".into()), url });
+        assert!(service.verify_consistency());
     }
 
     #[test]
-    fn test_duplicate_slug_creation_should_fail() {
+    fn test_recent_links_include_renamed_link() {
         let mut service = UrlShortenerService::new();
-        let url1 = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF8".to_string());
-        let slug = Slug("my_slug".to_string());
+        let url = Url("https://example.com".into());
+        for slug in ["a", "b"] {
+            service.handle_create_short_link(url.clone(), Some(Slug(slug.into()))).unwrap();
+        }
+        service.handle_rename_slug(Slug("a".into()), Slug("z".into())).unwrap();
 
-        service.handle_create_short_link(url1.clone(), Some(slug.clone())).unwrap();
+        let slugs: Vec<_> = service.recent_links(2).into_iter().map(|link| link.slug).collect();
 
-        let url2 = Url("https://www.amazon.com/Redragon-Keyboard-Wireless-Independent-Multimedia/dp/B0CTMMN857/ref=pd_ci_mcx_pspc_dp_2_i_2?pd_rd_w=J8fu0".to_string());
-        let result = service.handle_create_short_link(url2, Some(slug));
-        assert!(matches!(result, Err(ShortenerError::SlugAlreadyInUse)));
+        assert_eq!(slugs, vec![Slug("b".into()), Slug("z".into())]);
     }
 
     #[test]
-    fn test_redirect_increases_click_count_for_existing_slug() {
+    fn test_original_url_follows_renamed_slug() {
         let mut service = UrlShortenerService::new();
-        let url = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF".to_string());
-        let slug = Slug("my_slug".to_string());
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link(url.clone(), Some(Slug("old".into()))).unwrap();
+        service.handle_change_short_link(Slug("old".into()), Url("https://example.org".into())).unwrap();
+        service.handle_rename_slug(Slug("old".into()), Slug("new".into())).unwrap();
 
-        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+        assert_eq!(service.original_url(Slug("new".into())), Ok(url));
+        assert_eq!(service.original_url(Slug("old".into())), Err(ShortenerError::SlugNotFound));
+    }
 
-        let result = service.handle_redirect(slug.clone());
-        assert!(result.is_ok());
+    #[test]
+    fn test_meta_and_referrers_resolve_alias() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let alias = Slug("my_alias".into());
+        let meta = HashMap::from([("team".to_string(), "growth".to_string())]);
+        service
+            .handle_create_short_link_with_meta(Url("https://example.com".into()), Some(slug.clone()), meta.clone())
+            .unwrap();
+        service.handle_create_alias(slug, alias.clone()).unwrap();
+        service.handle_redirect_with_referrer(alias.clone(), Some("news".into())).unwrap();
 
-        let short_link = result.unwrap();
-        assert_eq!(short_link.url, url);
-        assert_eq!(short_link.slug, slug);
-        assert_eq!(service.click_counts.get(&slug), Some(&1));
+        assert_eq!(service.get_meta(alias.clone()), Ok(meta));
+        assert_eq!(service.referrers(alias), Ok(HashMap::from([("news".to_string(), 1)])));
     }
 
     #[test]
-    fn test_redirect_fails_for_nonexistent_slug() {
+    fn test_country_breakdown_resolves_alias() {
         let mut service = UrlShortenerService::new();
-        let slug = Slug("slug_does_not_exist".to_string());
+        let slug = Slug("my_slug".into());
+        let alias = Slug("my_alias".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_create_alias(slug, alias.clone()).unwrap();
+        service.handle_redirect_with_country(alias.clone(), Some("DE".into())).unwrap();
 
-        let result = service.handle_redirect(slug);
-        assert!(matches!(result, Err(ShortenerError::SlugNotFound)));
+        assert_eq!(service.country_breakdown(alias), Ok(HashMap::from([("DE".to_string(), 1)])));
     }
 
     #[test]
-    fn test_stats_retrieval_reflects_actual_clicks_for_valid_slug() {
+    fn test_event_listener_skips_untracked_redirects() {
+        let config = Config { track_redirect_events: false, ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&captured);
+        service.set_event_listener(move |event| sink.lock().unwrap().push(format!("{:?}", event.kind())));
+        let slug = Slug("my_slug".into());
+
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug).unwrap();
+
+        assert_eq!(*captured.lock().unwrap(), vec!["LinkCreated"]);
+    }
+
+    #[test]
+    fn test_redirect_resolved_counts_nothing_if_middle_hop_fails() {
+        let mut service = UrlShortenerService::builder().base_url("https://sho.rt/").build().unwrap();
+        service
+            .handle_create_short_link(Url("https://example.com".into()), Some(Slug("c".into())))
+            .unwrap();
+        service
+            .handle_create_short_link(Url("https://sho.rt/c".into()), Some(Slug("b".into())))
+            .unwrap();
+        service
+            .handle_create_short_link(Url("https://sho.rt/b".into()), Some(Slug("a".into())))
+            .unwrap();
+        service.handle_expire_now(Slug("b".into())).unwrap();
+
+        assert_eq!(
+            service.handle_redirect_resolved(Slug("a".into()), 2),
+            Err(ShortenerError::LinkExpired),
+        );
+        for slug in ["a", "c"] {
+            assert_eq!(service.get_stats(Slug(slug.into())).unwrap().redirects, 0);
+        }
+        assert_eq!(service.event_count(), 4);
+    }
+
+    #[test]
+    fn test_change_with_new_slug_resolves_alias() {
         let mut service = UrlShortenerService::new();
-        let url = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF".to_string());
-        let slug = Slug("my_slug".to_string());
+        let slug = Slug("my_slug".into());
+        let alias = Slug("my_alias".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_create_alias(slug.clone(), alias.clone()).unwrap();
+
+        let link = service.handle_change_with_new_slug(alias.clone(), Url("https://example.org".into())).unwrap();
+
+        assert_eq!(service.get_stats(slug), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.get_stats(alias), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.get_stats(link.slug).unwrap().link.url, Url("https://example.org".into()));
+        assert!(service.verify_consistency());
+    }
+
+    #[test]
+    fn test_percent_encode_slugs_mode() {
+        struct NonEmptyPolicy;
+
+        impl SlugPolicy for NonEmptyPolicy {
+            fn is_valid(&self, slug: &str) -> bool {
+                !slug.is_empty()
+            }
+        }
+
+        let mut service = UrlShortenerService::builder().percent_encode_slugs(true).build().unwrap();
+        service.set_slug_policy(NonEmptyPolicy);
+        let url = Url("https://example.com".into());
+
+        let link = service.handle_create_short_link(url.clone(), Some(Slug("my slug#1".into()))).unwrap();
+        assert_eq!(link.slug, Slug("my%20slug%231".into()));
+        let clean = service.handle_create_short_link(url.clone(), Some(Slug("clean".into()))).unwrap();
+        assert_eq!(clean.slug, Slug("clean".into()));
+
+        assert_eq!(service.handle_redirect(Slug("my slug#1".into())).unwrap().url, url);
+        assert_eq!(service.handle_redirect(Slug("my%20slug%231".into())).unwrap().url, url);
+        assert_eq!(service.get_stats(link.slug).unwrap().redirects, 2);
+        assert_eq!(
+            service.handle_create_short_link(url, Some(Slug("my slug#1".into()))),
+            Err(ShortenerError::SlugAlreadyInUse),
+        );
+    }
+
+    #[test]
+    fn test_service_is_send_and_sync() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+
+        assert_send::<UrlShortenerService>();
+        assert_sync::<UrlShortenerService>();
+    }
+
+    #[test]
+    fn test_dedupe_ignores_links_of_namespaces() {
+        let config = Config { dedupe_urls: true, ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link_ns("tenant", url.clone(), Some(Slug("abc".into()))).unwrap();
+
+        let link = service.handle_create_short_link(url.clone(), None).unwrap();
+        assert_ne!(link.slug, Slug("tenant/abc".into()));
+        assert_eq!(service.handle_create_short_link(url.clone(), None).unwrap(), link);
+
+        let scoped = service.handle_create_short_link_ns("tenant", url.clone(), None).unwrap();
+        assert_eq!(scoped, ShortLink { slug: Slug("abc".into()), url });
+    }
+
+    #[test]
+    fn test_namespaced_link_is_accessible_by_scoped_slug() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link_ns("tenant", url.clone(), Some(Slug("abc".into()))).unwrap();
+
+        assert_eq!(service.handle_redirect(Slug("abc".into())), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.handle_redirect(Slug("tenant/abc".into())).unwrap().url, url);
+        assert_eq!(service.get_stats_ns("tenant", Slug("abc".into())).unwrap().redirects, 1);
+    }
+
+    #[test]
+    fn test_restore_of_renamed_slug_fails() {
+        let mut service = UrlShortenerService::new();
+        let url = Url("https://example.com".into());
+        service.handle_create_short_link(url.clone(), Some(Slug("a".into()))).unwrap();
+        service.handle_rename_slug(Slug("a".into()), Slug("b".into())).unwrap();
+
+        assert_eq!(service.restore(Slug("a".into())), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.get_stats(Slug("a".into())), Err(ShortenerError::SlugNotFound));
+
+        service.handle_delete_short_link(Slug("b".into())).unwrap();
+        assert_eq!(service.restore(Slug("a".into())), Err(ShortenerError::SlugNotFound));
+        assert_eq!(service.restore(Slug("b".into())).unwrap(), ShortLink { slug: Slug("b".into()), url });
+        assert!(service.verify_consistency());
+    }
 
+    #[test]
+    fn test_replays_start_from_trimmed_events() {
+        let config = Config { max_events: Some(3), ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let slug = Slug("my_slug".into());
+        let url = Url("https://example.com".into());
         service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
-        service.handle_redirect(slug.clone()).unwrap();
-        service.handle_redirect(slug.clone()).unwrap();
+        for _ in 0..10 {
+            service.handle_redirect(slug.clone()).unwrap();
+        }
 
-        let result = service.get_stats(slug.clone());
-        assert!(result.is_ok());
+        assert!(service.verify_consistency());
+        service.rebuild_click_counts();
+        assert_eq!(service.get_stats(slug.clone()).unwrap().redirects, 10);
+        assert_eq!(service.original_url(slug.clone()), Ok(url));
 
-        let stats = result.unwrap();
-        assert_eq!(stats.link.url, url);
-        assert_eq!(stats.redirects, 2);
+        let state = service.state_at(9);
+        assert_eq!(state.get_stats(slug.clone()).unwrap().redirects, 9);
+        assert_eq!(state.event_count(), 10);
+        assert_eq!(service.state_at(0).get_stats(slug).unwrap().redirects, 7);
     }
 
     #[test]
-    fn test_stats_retrieval_fails_gracefully_for_missing_slug() {
-        let service = UrlShortenerService::new();
-        let slug = Slug("slug_does_not_exist".to_string());
+    fn test_rebuild_click_counts_keeps_untracked_redirects() {
+        let config = Config { track_redirect_events: false, ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        for _ in 0..3 {
+            service.handle_redirect(slug.clone()).unwrap();
+        }
 
-        let result = service.get_stats(slug);
-        assert!(matches!(result, Err(ShortenerError::SlugNotFound)));
+        service.rebuild_click_counts();
+
+        assert_eq!(service.get_stats(slug).unwrap().redirects, 3);
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn test_change_short_link_success() {
+    fn test_loaded_state_is_consistent() {
         let mut service = UrlShortenerService::new();
-        let original_url = Url("https://www.amazon.com/Redragon-S101-Keyboard-Ergonomic-Programmable/dp/B00NLZUM36/ref=sr_1_1?_encoding=UTF8".into());
-        let new_url = Url("https://www.amazon.com/Redragon-Keyboard-Wireless-Independent-Multimedia/dp/B0CTMMN857/ref=pd_ci_mcx_pspc_dp_2_i_2?pd_rd_w=J8fu0".into());
         let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        service.handle_redirect(slug.clone()).unwrap();
 
-        // Create the initial short link
-        service.handle_create_short_link(original_url.clone(), Some(slug.clone())).unwrap();
+        let mut loaded = UrlShortenerService::load_state(&service.save_state()).unwrap();
+        loaded.handle_redirect(slug.clone()).unwrap();
 
-        // Change the short link's destination URL
-        let result = service.handle_change_short_link(slug.clone(), new_url.clone());
+        assert!(loaded.verify_consistency());
+        loaded.rebuild_click_counts();
+        assert_eq!(loaded.get_stats(slug).unwrap().redirects, 2);
+    }
 
-        assert!(result.is_ok());
-        let updated_short_link = result.unwrap();
-        assert_eq!(updated_short_link.url, new_url);
-        assert_eq!(updated_short_link.slug, slug);
+    #[test]
+    fn test_original_url_of_recreated_slug_is_latest() {
+        let mut service = UrlShortenerService::new();
+        let slug = Slug("my_slug".into());
+        let first = Url("https://example.com".into());
+        let second = Url("https://example.org".into());
+        service.handle_create_short_link(first.clone(), Some(slug.clone())).unwrap();
+        service.handle_delete_short_link(slug.clone()).unwrap();
+        assert_eq!(service.original_url(slug.clone()), Ok(first));
 
-        // Verify that the internal state reflects the change
-        let stats = service.get_stats(slug.clone()).unwrap();
-        assert_eq!(stats.link.url, new_url);
+        service.handle_create_short_link(second.clone(), Some(slug.clone())).unwrap();
+        service.handle_change_short_link(slug.clone(), Url("https://example.net".into())).unwrap();
+
+        assert_eq!(service.original_url(slug), Ok(second));
     }
 
     #[test]
-    fn test_change_short_link_slug_not_found() {
+    fn test_per_slug_apis_resolve_alias() {
         let mut service = UrlShortenerService::new();
-        let new_url = Url("https://www.amazon.com/Redragon-Keyboard-Wireless-Independent-Multimedia/dp/B0CTMMN857/ref=pd_ci_mcx_pspc_dp_2_i_2?pd_rd_w=J8fu0".into());
-        let slug = Slug("slug_does_not_exist".into());
+        let slug = Slug("my_slug".into());
+        let alias = Slug("my_alias".into());
+        let url = Url("https://example.com".into());
+        let new_url = Url("https://example.org".into());
+        service.handle_create_short_link(url.clone(), Some(slug.clone())).unwrap();
+        service.handle_create_alias(slug.clone(), alias.clone()).unwrap();
 
-        // Attempt to change a non-existent short link
-        let result = service.handle_change_short_link(slug.clone(), new_url.clone());
+        assert_eq!(service.original_url(alias.clone()), Ok(url.clone()));
+        assert_eq!(service.timeline(alias.clone()).unwrap().len(), 2);
+        assert_eq!(service.event_counts_for(&alias), (1, 0));
 
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ShortenerError::SlugNotFound);
+        let changed = service.handle_change_short_link(alias.clone(), new_url.clone()).unwrap();
+        assert_eq!(changed, ShortLink { slug: slug.clone(), url: new_url.clone() });
+        let changed = service.handle_change_if(alias.clone(), new_url.clone(), url.clone()).unwrap();
+        assert_eq!(changed, ShortLink { slug: slug.clone(), url: url.clone() });
+
+        let renamed = service.handle_rename_slug(alias.clone(), Slug("renamed".into())).unwrap();
+        assert_eq!(renamed, ShortLink { slug: Slug("renamed".into()), url });
+        assert_eq!(service.get_stats(alias).unwrap().link.slug, Slug("renamed".into()));
+        assert_eq!(service.get_stats(slug), Err(ShortenerError::SlugNotFound));
+        assert!(service.verify_consistency());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_load_state_without_newer_config_fields() {
+        let config = Config { max_url_len: 64, ..Config::default() };
+        let mut service = UrlShortenerService::with_config(config);
+        let slug = Slug("my_slug".into());
+        service.handle_create_short_link(Url("https://example.com".into()), Some(slug.clone())).unwrap();
+        let mut state: serde_json::Value = serde_json::from_str(&service.save_state()).unwrap();
+        let config = state["config"].as_object_mut().unwrap();
+        config.remove("percent_encode_slugs").unwrap();
+        config.remove("immutable_links").unwrap();
+
+        let loaded = UrlShortenerService::load_state(&state.to_string()).unwrap();
+
+        assert_eq!(loaded.config.max_url_len, 64);
+        assert!(!loaded.config.percent_encode_slugs);
+        assert_eq!(loaded.get_stats(slug).unwrap().link.url, Url("https://example.com".into()));
     }
-}
\ No newline at end of file
+}